@@ -1,18 +1,30 @@
 use grid;
 use noise_lib;
+use error;
 use geom::{Geometry, GeometryProvider};
 use cgmath::{Matrix4, Vector3};
 use visualizer::{Index, Vertex, Visualizer};
-use glium;
 
+/// Seconds taken by an animation to move the slice from one end of the noise to
+/// the other, by default.
+pub const DEFAULT_SWEEP_DURATION: f64 = 15.0;
+
+/// Shows a 2D slice moving back and forth through 3D noise.
+///
+/// The position of the slice follows `Visualizer::time`, so the animation runs at
+/// the same speed whatever the frame rate.
 pub struct PerlinAnimation<N>
 where
     N: noise_lib::noise::Noise<IndexType = Vector3<f64>>,
 {
     noise: N,
     current_geom: Option<Geometry<Vertex, Index>>,
-    current_frame: u32,
+    sweep_duration: f64,
     dimensions: (u32, u32),
+    model: Matrix4<f32>,
+    mesh_options: grid::MeshOptions,
+    normalization: grid::Normalization,
+    raw_range: Option<(f64, f64)>,
 }
 
 impl<N> PerlinAnimation<N>
@@ -23,29 +35,58 @@ where
         PerlinAnimation {
             noise,
             current_geom: None,
-            current_frame: 0,
+            sweep_duration: DEFAULT_SWEEP_DURATION,
             dimensions,
+            model: Matrix4::from_translation(Vector3::new(0.0, 0.0, 20.0_f32))
+                * Matrix4::from_nonuniform_scale(1.0, 1.0, (dimensions.0 as f32) / 2.0),
+            mesh_options: grid::MeshOptions::default(),
+            normalization: grid::Normalization::default(),
+            raw_range: None,
         }
     }
 
-    fn build_geometry(&mut self, vis: &Visualizer, z: f64) {
-        let slice = noise_lib::slice::Slice2d::new(&self.noise, z);
+    pub fn set_model(&mut self, model: Matrix4<f32>) -> &mut PerlinAnimation<N> {
+        self.model = model;
+        self
+    }
 
-        let grid = grid::make_noise_grid(&slice, self.dimensions);
-        let (vertices, indices) = grid.gen_vertex_buffer();
+    pub fn set_mesh_options(&mut self, options: grid::MeshOptions) -> &mut PerlinAnimation<N> {
+        self.mesh_options = options;
+        self
+    }
 
-        let model = Matrix4::from_translation(Vector3::new(0.0, 0.0, 20.0_f32))
-            * Matrix4::from_nonuniform_scale(1.0, 1.0, (self.dimensions.0 as f32) / 2.0);
+    /// Sets how each frame is mapped to heights. `Normalization::MinMax` stretches
+    /// every frame on its own, which hides the changes in overall height that
+    /// `Clamp` shows.
+    pub fn set_normalization(
+        &mut self,
+        normalization: grid::Normalization,
+    ) -> &mut PerlinAnimation<N> {
+        self.normalization = normalization;
+        self
+    }
 
-        let vertex_buffer = glium::VertexBuffer::new(vis.display(), &vertices).unwrap();
-        let index_buffer = glium::IndexBuffer::new(
-            vis.display(),
-            glium::index::PrimitiveType::TrianglesList,
-            &indices,
-        ).unwrap();
+    /// Sets the seconds taken to move the slice through the noise once; a full
+    /// cycle back and forth takes twice as long.
+    pub fn set_sweep_duration(&mut self, seconds: f64) -> &mut PerlinAnimation<N> {
+        assert!(seconds > 0.0);
+        self.sweep_duration = seconds;
+        self
+    }
 
-        let g = Geometry::new(vertex_buffer, index_buffer, model);
+    fn build_geometry(&mut self, vis: &Visualizer, z: f64) -> error::Result<()> {
+        let slice = noise_lib::slice::Slice2d::new(&self.noise, z);
+
+        let grid = grid::make_noise_grid(&slice, self.dimensions, self.normalization);
+        self.raw_range = Some(grid.raw_range());
+        let (vertices, indices) = match vis.shading() {
+            grid::Shading::Smooth => grid.gen_vertex_buffer_with(&self.mesh_options),
+            grid::Shading::Flat => grid.gen_flat_vertex_buffer(&self.mesh_options),
+        };
+
+        let g = Geometry::from_mesh(vis.display(), &vertices, &indices, self.model)?;
         self.current_geom = Some(g);
+        Ok(())
     }
 }
 
@@ -53,19 +94,18 @@ impl<N> GeometryProvider<Vertex, Index> for PerlinAnimation<N>
 where
     N: noise_lib::noise::Noise<IndexType = Vector3<f64>, DimType = (u32, u32, u32)>,
 {
-    fn get_geometry(&self) -> &Geometry<Vertex, Index> {
-        self.current_geom.as_ref().unwrap()
+    fn geometries(&self) -> Vec<&Geometry<Vertex, Index>> {
+        self.current_geom.iter().collect()
     }
 
-    fn update(&mut self, vis: &Visualizer) {
-        self.current_frame += 1;
-        let distance = (self.current_frame % 1000) as f64 / 1000.0;
-        let z = if self.current_frame % 2000 >= 1000 {
-            1.0 - distance
-        } else {
-            distance
-        };
+    fn raw_range(&self) -> Option<(f64, f64)> {
+        self.raw_range
+    }
+
+    fn update(&mut self, vis: &Visualizer) -> error::Result<()> {
+        let phase = (vis.time() / self.sweep_duration) % 2.0;
+        let z = if phase >= 1.0 { 2.0 - phase } else { phase };
 
-        self.build_geometry(vis, z);
+        self.build_geometry(vis, z)
     }
 }
@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use image::{self, Rgba, RgbaImage};
+
+/// The directory next to the executable that is searched after the configured ones.
+const BUNDLED_DIR: &str = "Assets";
+
+/// The textures of `LayerConfig::defaults` built into the binary, by file name.
+#[cfg(feature = "embedded-assets")]
+const EMBEDDED: &[(&str, &[u8])] = &[
+    (
+        "grass_texture_1.jpg",
+        include_bytes!("../Assets/grass_texture_1.jpg"),
+    ),
+    (
+        "Orange dirtl texture-1.jpg",
+        include_bytes!("../Assets/Orange dirtl texture-1.jpg"),
+    ),
+    (
+        "stone_texture_1.jpg",
+        include_bytes!("../Assets/stone_texture_1.jpg"),
+    ),
+];
+
+/// The width and height of placeholders when there is no other texture to match.
+pub const PLACEHOLDER_SIZE: u32 = 64;
+/// The number of squares across a placeholder.
+const PLACEHOLDER_SQUARES: u32 = 8;
+
+#[derive(Debug)]
+pub enum AssetError {
+    /// The file wasn't at its path or in any of the directories searched.
+    NotFound(PathBuf, Vec<PathBuf>),
+    Io(PathBuf, io::Error),
+    Decode(PathBuf, image::ImageError),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssetError::NotFound(ref path, ref dirs) => {
+                write!(f, "could not find {}", path.display())?;
+                if !dirs.is_empty() {
+                    let dirs: Vec<_> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+                    write!(f, " (also searched {})", dirs.join(", "))?;
+                }
+                Ok(())
+            }
+            AssetError::Io(ref path, ref err) => {
+                write!(f, "could not read {}: {}", path.display(), err)
+            }
+            AssetError::Decode(ref path, ref err) => {
+                write!(f, "could not decode {}: {}", path.display(), err)
+            }
+        }
+    }
+}
+
+impl error::Error for AssetError {
+    fn description(&self) -> &str {
+        match *self {
+            AssetError::NotFound(..) => "asset not found",
+            AssetError::Io(..) => "could not read asset",
+            AssetError::Decode(..) => "could not decode asset",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            AssetError::NotFound(..) => None,
+            AssetError::Io(_, ref err) => Some(err),
+            AssetError::Decode(_, ref err) => Some(err),
+        }
+    }
+}
+
+/// Finds and decodes the files used by the visualizer, such as textures.
+///
+/// When built with the `embedded-assets` feature, files that aren't found on disk
+/// are taken from the copies of `Assets` in the binary, so files on disk always
+/// take precedence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetLoader {
+    dirs: Vec<PathBuf>,
+}
+
+impl AssetLoader {
+    /// Searches `dirs` in order for files that aren't found at their own path, and
+    /// then the `Assets` directory next to the executable.
+    pub fn new(dirs: Vec<PathBuf>) -> AssetLoader {
+        let mut dirs = dirs;
+        let bundled = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(BUNDLED_DIR)));
+        if let Some(bundled) = bundled {
+            if !dirs.contains(&bundled) {
+                dirs.push(bundled);
+            }
+        }
+        AssetLoader { dirs }
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// The first existing file out of `path` itself, and `path` and then its file
+    /// name alone in each of the directories.
+    pub fn find(&self, path: &Path) -> Result<PathBuf, AssetError> {
+        if path.is_file() {
+            return Ok(path.to_path_buf());
+        }
+
+        for dir in &self.dirs {
+            let mut candidates = vec![dir.join(path)];
+            if let Some(name) = path.file_name() {
+                candidates.push(dir.join(name));
+            }
+            if let Some(found) = candidates.into_iter().find(|path| path.is_file()) {
+                return Ok(found);
+            }
+        }
+        Err(AssetError::NotFound(path.to_path_buf(), self.dirs.clone()))
+    }
+
+    /// Loads the image at `path`, with its format detected from its contents.
+    pub fn load_image(&self, path: &Path) -> Result<RgbaImage, AssetError> {
+        let (path, bytes) = match self.find(path) {
+            Ok(path) => {
+                let mut bytes = Vec::new();
+                fs::File::open(&path)
+                    .and_then(|mut file| file.read_to_end(&mut bytes))
+                    .map_err(|err| AssetError::Io(path.clone(), err))?;
+                (path, Cow::Owned(bytes))
+            }
+            Err(err) => match embedded(path) {
+                Some(bytes) => (path.to_path_buf(), Cow::Borrowed(bytes)),
+                None => return Err(err),
+            },
+        };
+
+        image::load_from_memory(&bytes)
+            .map(|img| img.to_rgba())
+            .map_err(|err| AssetError::Decode(path, err))
+    }
+}
+
+/// The embedded copy of the file with the same name as `path`.
+#[cfg(feature = "embedded-assets")]
+fn embedded(path: &Path) -> Option<&'static [u8]> {
+    let name = path.file_name()?;
+    EMBEDDED
+        .iter()
+        .find(|&&(embedded_name, _)| name == embedded_name)
+        .map(|&(_, bytes)| bytes)
+}
+
+#[cfg(not(feature = "embedded-assets"))]
+fn embedded(_path: &Path) -> Option<&'static [u8]> {
+    None
+}
+
+/// A checkerboard of `color` and a darker shade of it, `size` pixels wide and high,
+/// to stand in for a texture that couldn't be loaded.
+pub fn placeholder_texture(color: [f32; 4], size: (u32, u32)) -> RgbaImage {
+    let to_pixel = |shade: f32| {
+        let mut pixel = [255; 4];
+        for i in 0..3 {
+            pixel[i] = (color[i].max(0.0).min(1.0) * shade * 255.0).round() as u8;
+        }
+        Rgba(pixel)
+    };
+    let (light, dark) = (to_pixel(1.0), to_pixel(0.75));
+
+    let square = (size.0 / PLACEHOLDER_SQUARES).max(1);
+    RgbaImage::from_fn(size.0, size.1, |x, y| {
+        if (x / square + y / square) % 2 == 0 {
+            light
+        } else {
+            dark
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// An empty directory of its own for each test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("asset-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn files_at_their_own_path_come_first() {
+        let root = temp_dir("own-path");
+        let (own, dir) = (root.join("own/grass.png"), root.join("dir"));
+        touch(&own);
+        touch(&dir.join("grass.png"));
+
+        let loader = AssetLoader::new(vec![dir]);
+        assert_eq!(loader.find(&own).unwrap(), own);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn asset_dirs_are_searched_in_order() {
+        let root = temp_dir("search-order");
+        let (first, second) = (root.join("first"), root.join("second"));
+        touch(&first.join("grass.png"));
+        touch(&second.join("grass.png"));
+        touch(&second.join("textures/rock.png"));
+        touch(&second.join("rock.png"));
+
+        let loader = AssetLoader::new(vec![first.clone(), second.clone()]);
+        assert_eq!(loader.dirs()[..2], [first.clone(), second.clone()]);
+        // A path is looked for as it is in each directory, and then by name.
+        assert_eq!(
+            loader.find(Path::new("missing/grass.png")).unwrap(),
+            first.join("grass.png")
+        );
+        assert_eq!(
+            loader.find(Path::new("textures/rock.png")).unwrap(),
+            second.join("textures/rock.png")
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn missing_files_fall_back_to_embedded_assets() {
+        let root = temp_dir("embedded");
+        let loader = AssetLoader::new(vec![root.clone()]);
+
+        let missing = Path::new("missing/grass_texture_1.jpg");
+        match loader.find(missing) {
+            Err(AssetError::NotFound(ref path, ref dirs)) => {
+                assert_eq!(path, missing);
+                assert_eq!(dirs[0], root);
+            }
+            other => panic!("expected the file not to be found, got {:?}", other),
+        }
+        // Only the default textures are embedded.
+        assert_eq!(
+            loader.load_image(missing).is_ok(),
+            cfg!(feature = "embedded-assets")
+        );
+        match loader.load_image(Path::new("missing/snow.png")) {
+            Err(AssetError::NotFound(..)) => (),
+            other => panic!("expected the file not to be found, got {:?}", other),
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn images_are_decoded_by_content() {
+        let root = temp_dir("decode");
+        let (image_path, html_path) = (root.join("image.jpg"), root.join("page.jpg"));
+        placeholder_texture([0.2, 0.4, 0.6, 1.0], (16, 8))
+            .save(root.join("image.png"))
+            .unwrap();
+        fs::rename(root.join("image.png"), &image_path).unwrap();
+        fs::write(&html_path, b"<!DOCTYPE html>").unwrap();
+
+        let loader = AssetLoader::new(Vec::new());
+        let img = loader.load_image(&image_path).unwrap();
+        assert_eq!(img.dimensions(), (16, 8));
+        match loader.load_image(&html_path) {
+            Err(AssetError::Decode(..)) => (),
+            other => panic!("expected a decoding error, got {:?}", other),
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
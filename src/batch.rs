@@ -0,0 +1,198 @@
+use std::f64;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use builder;
+use config::Config;
+use error::{self, Error};
+use export;
+use grid::Grid;
+use scene::SceneFile;
+use simplify;
+
+/// Terrains to generate without opening a window.
+///
+/// Every config is generated once per seed, and the results are written to `output`
+/// as `<name>_<seed>.<ext>` files, along with a `stats.csv` summary of all of them.
+pub struct BatchJob {
+    pub output: PathBuf,
+    /// Seeds to generate each config with; if empty, each config uses its own seed.
+    pub seeds: Vec<u64>,
+    /// The configs to generate, with the names used for their files.
+    pub configs: Vec<(String, Config)>,
+    pub exports: Exports,
+}
+
+/// The files written for each terrain of a `BatchJob`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exports {
+    /// A 16-bit grayscale PNG of the grid.
+    pub heightmap: bool,
+    /// The smooth-shaded mesh as an OBJ file, in world coordinates, simplified when
+    /// the config has a maximum error.
+    pub mesh: bool,
+    /// A scene file that reproduces the terrain.
+    pub scene: bool,
+}
+
+impl Exports {
+    pub const NAMES: &'static [&'static str] = &["heightmap", "mesh", "scene"];
+
+    pub fn none() -> Exports {
+        Exports {
+            heightmap: false,
+            mesh: false,
+            scene: false,
+        }
+    }
+}
+
+impl Default for Exports {
+    fn default() -> Exports {
+        Exports {
+            heightmap: true,
+            mesh: true,
+            scene: true,
+        }
+    }
+}
+
+/// Summary of the values of a grid.
+///
+/// Values that are NaN or infinite are counted in `non_finite` and left out of the
+/// rest, which are all NaN if no value is finite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub median: f64,
+    pub non_finite: usize,
+}
+
+impl GridStats {
+    pub fn new(grid: &Grid) -> GridStats {
+        let mut values: Vec<f64> = grid.as_ref()
+            .iter()
+            .cloned()
+            .filter(|value| value.is_finite())
+            .collect();
+        let non_finite = grid.as_ref().len() - values.len();
+        if values.is_empty() {
+            return GridStats {
+                min: f64::NAN,
+                max: f64::NAN,
+                mean: f64::NAN,
+                std_dev: f64::NAN,
+                median: f64::NAN,
+                non_finite,
+            };
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let count = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / count;
+        let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / count;
+
+        GridStats {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            median: values[values.len() / 2],
+            non_finite,
+        }
+    }
+}
+
+/// Runs every terrain of `job`, printing a line for each one as it is written.
+pub fn run(job: &BatchJob) -> error::Result<()> {
+    fs::create_dir_all(&job.output).map_err(|err| Error::Io(job.output.clone(), err))?;
+
+    let stats_path = job.output.join("stats.csv");
+    let stats_error = |err: io::Error| Error::Io(stats_path.clone(), err);
+    let mut stats_file = File::create(&stats_path)
+        .map(BufWriter::new)
+        .map_err(&stats_error)?;
+    writeln!(
+        stats_file,
+        "name,mode,seed,width,height,min,max,mean,std_dev,median,non_finite"
+    ).map_err(&stats_error)?;
+
+    for &(ref name, ref config) in &job.configs {
+        let seeds = if job.seeds.is_empty() {
+            vec![config.noise.seed.unwrap_or_else(builder::random_seed)]
+        } else {
+            job.seeds.clone()
+        };
+
+        for seed in seeds {
+            let mut config = config.clone();
+            config.noise.seed = Some(seed);
+            let stem = format!("{}_{}", name, seed);
+
+            let grid = builder::build_grid(&config);
+            write_exports(&job.output, &stem, &config, &grid, job.exports)?;
+
+            let stats = GridStats::new(&grid);
+            writeln!(
+                stats_file,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                stem,
+                config.mode,
+                seed,
+                grid.width(),
+                grid.height(),
+                stats.min,
+                stats.max,
+                stats.mean,
+                stats.std_dev,
+                stats.median,
+                stats.non_finite
+            ).map_err(&stats_error)?;
+            if stats.non_finite > 0 {
+                eprintln!(
+                    "warning: {}: {} values are not finite numbers",
+                    stem, stats.non_finite
+                );
+            }
+            println!(
+                "{}: mean {:.4}, std dev {:.4}, median {:.4}",
+                stem, stats.mean, stats.std_dev, stats.median
+            );
+        }
+    }
+
+    stats_file.flush().map_err(&stats_error)
+}
+
+fn write_exports(
+    dir: &Path,
+    stem: &str,
+    config: &Config,
+    grid: &Grid,
+    exports: Exports,
+) -> error::Result<()> {
+    if exports.heightmap {
+        let path = dir.join(format!("{}.png", stem));
+        export::write_heightmap_png(grid, &path).map_err(|err| Error::Io(path, err))?;
+    }
+    if exports.mesh {
+        let path = dir.join(format!("{}.obj", stem));
+        let (vertices, indices) = match config.max_error {
+            Some(max_error) => simplify::simplify_grid(grid, max_error),
+            None => grid.gen_vertex_buffer_with(&config.mesh),
+        };
+        export::write_obj_file(&path, &vertices, &indices, config.model_matrix())
+            .map_err(|err| Error::Io(path, err))?;
+    }
+    if exports.scene {
+        let path = dir.join(format!("{}.toml", stem));
+        File::create(&path)
+            .and_then(|mut file| file.write_all(SceneFile::from_config(config).to_toml().as_bytes()))
+            .map_err(|err| Error::Io(path, err))?;
+    }
+    Ok(())
+}
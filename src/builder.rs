@@ -0,0 +1,161 @@
+use cgmath::Vector2;
+use noise_lib;
+use rand::{self, SeedableRng};
+
+use animated;
+use chunked;
+use config::{Config, InterpolatorKind, Mode};
+use geom::GeometryProvider;
+use grid::{self, Normalization};
+use static_terrain;
+use streaming;
+use visualizer::{Index, Vertex};
+
+/// Evaluates `$body` with `$interp` bound to the interpolator selected by `$kind`.
+///
+/// Each interpolator gives the noise a different type, so the body is expanded
+/// once per interpolator instead of going through a trait object.
+macro_rules! with_interpolator {
+    ($kind:expr, |$interp:ident| $body:expr) => {
+        match $kind {
+            InterpolatorKind::Linear => {
+                let $interp = noise_lib::interpolate::LinearInterpolator::new();
+                $body
+            }
+            InterpolatorKind::Cubic => {
+                let $interp = noise_lib::interpolate::CubicInterpolator::new();
+                $body
+            }
+            InterpolatorKind::ImprovedPerlin => {
+                let $interp = noise_lib::interpolate::ImprovedPerlinInterpolator::new();
+                $body
+            }
+        }
+    };
+}
+
+/// Evaluates `$body` with `$noise` bound to the 2D noise described by `$config`.
+macro_rules! with_noise_2d {
+    ($config:expr, |$noise:ident| $body:expr) => {{
+        let config: &Config = $config;
+        let rng = make_rng(config.noise.seed.unwrap_or_else(random_seed));
+        let dims = &config.noise.dimensions;
+        with_interpolator!(config.noise.interpolator, |interpolator| {
+            let $noise = noise_lib::perlin::build_geometric_octaves(
+                (dims[0], dims[1]),
+                config.noise.octaves,
+                config.noise.persistence,
+                &mut noise_lib::perlin::RandomGradientBuilder2d::new(rng),
+                &interpolator,
+            );
+            $body
+        })
+    }};
+}
+
+/// Evaluates `$body` with `$noise` bound to the 3D noise described by `$config`.
+macro_rules! with_noise_3d {
+    ($config:expr, |$noise:ident| $body:expr) => {{
+        let config: &Config = $config;
+        let rng = make_rng(config.noise.seed.unwrap_or_else(random_seed));
+        let dims = &config.noise.dimensions;
+        with_interpolator!(config.noise.interpolator, |interpolator| {
+            let $noise = noise_lib::perlin3d::build_geometric_octaves(
+                (dims[0], dims[1], dims[2]),
+                config.noise.octaves,
+                config.noise.persistence,
+                &mut noise_lib::perlin3d::RandomGradientBuilder3d::new(rng),
+                &interpolator,
+            );
+            $body
+        })
+    }};
+}
+
+/// Builds the terrain described by `config`, from a random seed if it doesn't have one.
+pub fn build_geometry_provider(config: &Config) -> Box<GeometryProvider<Vertex, Index>> {
+    match config.mode {
+        Mode::Animated => with_noise_3d!(config, |noise| {
+            let mut animation = animated::PerlinAnimation::new(noise, config.grid_size);
+            animation
+                .set_model(config.model_matrix())
+                .set_mesh_options(config.mesh)
+                .set_normalization(config.normalization);
+            Box::new(animation) as Box<GeometryProvider<Vertex, Index>>
+        }),
+        _ => with_noise_2d!(config, |noise| build_2d_provider(noise, config)),
+    }
+}
+
+/// Samples the noise described by `config` into a grid of `config.grid_size`, without
+/// creating any geometry.
+///
+/// This is the grid static mode shows; animated mode gives the slice the animation starts from, and
+/// streaming mode the area covered by the grid size from the origin.
+pub fn build_grid(config: &Config) -> grid::Grid {
+    match config.mode {
+        Mode::Animated => with_noise_3d!(config, |noise| {
+            let slice = noise_lib::slice::Slice2d::new(&noise, 0.0);
+            grid::make_noise_grid(&slice, config.grid_size, config.normalization)
+        }),
+        _ => with_noise_2d!(config, |noise| {
+            grid::make_noise_grid(&noise, config.grid_size, config.normalization)
+        }),
+    }
+}
+
+fn build_2d_provider<N>(noise: N, config: &Config) -> Box<GeometryProvider<Vertex, Index>>
+where
+    N: noise_lib::noise::Noise<IndexType = Vector2<f64>> + 'static,
+{
+    match config.mode {
+        Mode::Chunked => {
+            let grid = grid::make_noise_grid(&noise, config.grid_size, config.normalization);
+
+            Box::new(chunked::ChunkedTerrain::new(
+                grid,
+                config.model_matrix(),
+                chunked::DEFAULT_CHUNK_SIZE,
+                chunked::DEFAULT_LOD_DISTANCES.to_vec(),
+            ))
+        }
+        Mode::Streaming => {
+            let mut terrain = streaming::StreamingTerrain::new(
+                noise,
+                config.model_matrix(),
+                streaming::DEFAULT_CHUNK_CELLS,
+                1.0 / f64::from(config.grid_size.0),
+                streaming::DEFAULT_VIEW_DISTANCE,
+            );
+            // Sampled values are already offset into [0, 1] like in `make_noise_grid`.
+            if config.normalization == Normalization::Clamp {
+                terrain.set_value_range((0.0, 1.0));
+            }
+            Box::new(terrain)
+        }
+        _ => {
+            let grid = grid::make_noise_grid(&noise, config.grid_size, config.normalization);
+            let mut terrain = static_terrain::StaticTerrain::new(grid, config.model_matrix());
+            terrain
+                .set_mesh_options(config.mesh)
+                .set_max_error(config.max_error);
+            Box::new(terrain)
+        }
+    }
+}
+
+/// Creates the generator for the noise gradients.
+///
+/// `Isaac64Rng` is used rather than `StdRng`, whose algorithm depends on the platform
+/// and the version of rand, so that a seed always gives the same terrain.
+pub fn make_rng(seed: u64) -> rand::Isaac64Rng {
+    rand::Isaac64Rng::from_seed(&[seed][..])
+}
+
+/// Draws a new seed.
+///
+/// The top bit is always clear: scene files store seeds as TOML integers, which are
+/// signed 64-bit, so a larger seed would be saved but could not be loaded again.
+pub fn random_seed() -> u64 {
+    rand::random::<u64>() & i64::max_value() as u64
+}
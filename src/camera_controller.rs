@@ -172,4 +172,11 @@ impl CameraController {
         self.up = up;
         return self;
     }
+    /// Turns the camera towards `target`, keeping its position.
+    pub fn look_at(&mut self, target: Vector3<f32>) -> &mut CameraController {
+        let facing = (target - self.position).normalize();
+        self.theta = facing.y.max(-1.0).min(1.0).acos();
+        self.phi = facing.z.atan2(facing.x);
+        return self;
+    }
 }
@@ -0,0 +1,168 @@
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+use error;
+use geom::{Geometry, GeometryProvider};
+use grid::{self, Grid, Shading};
+use visualizer::{Index, Vertex, Visualizer};
+
+/// Cells along each side of a tile in chunked mode.
+pub const DEFAULT_CHUNK_SIZE: u32 = 64;
+/// Camera distances at which tiles in chunked mode switch to coarser levels, in world
+/// units.
+pub const DEFAULT_LOD_DISTANCES: [f32; 4] = [30.0, 60.0, 120.0, 240.0];
+
+/// Splits a grid into square tiles, each meshed at several levels of detail.
+///
+/// Level `n` keeps every `2^n`th row and column of a tile. Every frame, each tile
+/// picks its level from the distance between the camera and the tile center, and
+/// skirts hung from the tile edges hide the cracks between tiles of different levels.
+pub struct ChunkedTerrain {
+    grid: Grid,
+    model: Matrix4<f32>,
+    chunk_size: u32,
+    lod_distances: Vec<f32>,
+    skirt_depth: f32,
+    shading: Shading,
+    chunks: Vec<Chunk>,
+}
+
+struct Chunk {
+    origin: (u32, u32),
+    cells: (u32, u32),
+    center: Vector3<f32>,
+    lods: Vec<Geometry<Vertex, Index>>,
+    current_lod: usize,
+}
+
+impl ChunkedTerrain {
+    /// Creates a terrain of `chunk_size` x `chunk_size` cell tiles.
+    ///
+    /// `lod_distances` are the camera distances, in world units, at which tiles
+    /// switch to the next coarser level, so there are `lod_distances.len() + 1` levels.
+    pub fn new(
+        grid: Grid,
+        model: Matrix4<f32>,
+        chunk_size: u32,
+        lod_distances: Vec<f32>,
+    ) -> ChunkedTerrain {
+        assert!(chunk_size > 0);
+
+        let mut chunks = Vec::new();
+        let (cells_x, cells_y) = (grid.width() - 1, grid.height() - 1);
+
+        for y in (0..cells_y).filter(|y| y % chunk_size == 0) {
+            for x in (0..cells_x).filter(|x| x % chunk_size == 0) {
+                let cells = (chunk_size.min(cells_x - x), chunk_size.min(cells_y - y));
+                let center_x = x + cells.0 / 2;
+                let center_y = y + cells.1 / 2;
+                let center = model
+                    * Vector4::new(
+                        center_x as f32,
+                        center_y as f32,
+                        grid.value(center_x, center_y) as f32,
+                        1.0,
+                    );
+
+                chunks.push(Chunk {
+                    origin: (x, y),
+                    cells,
+                    center: center.truncate(),
+                    lods: Vec::new(),
+                    current_lod: 0,
+                });
+            }
+        }
+
+        ChunkedTerrain {
+            grid,
+            model,
+            chunk_size,
+            lod_distances,
+            skirt_depth: 0.05,
+            shading: Shading::Smooth,
+            chunks,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+    pub fn num_lods(&self) -> usize {
+        self.lod_distances.len() + 1
+    }
+
+    /// Sets how far, in grid height units, the skirts hang below the tile edges.
+    ///
+    /// Only affects meshes built after the call.
+    pub fn set_skirt_depth(&mut self, depth: f32) -> &mut ChunkedTerrain {
+        self.skirt_depth = depth;
+        self
+    }
+
+    fn build_lods(&mut self, vis: &Visualizer) -> error::Result<()> {
+        let num_lods = self.num_lods();
+        if self.shading != vis.shading() {
+            self.shading = vis.shading();
+            for chunk in &mut self.chunks {
+                chunk.lods.clear();
+            }
+        }
+
+        for chunk in self.chunks.iter_mut().filter(|c| c.lods.is_empty()) {
+            for lod in 0..num_lods {
+                let (vertices, indices) = self.grid.gen_region_vertex_buffer(
+                    chunk.origin,
+                    chunk.cells,
+                    1 << lod,
+                    self.skirt_depth,
+                );
+                let (vertices, indices) = match self.shading {
+                    Shading::Smooth => (vertices, indices),
+                    Shading::Flat => grid::flatten_mesh(&vertices, &indices),
+                };
+
+                chunk.lods.push(Geometry::from_mesh(
+                    vis.display(),
+                    &vertices,
+                    &indices,
+                    self.model,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    fn select_lod(&self, distance: f32) -> usize {
+        self.lod_distances
+            .iter()
+            .take_while(|&&d| distance > d)
+            .count()
+    }
+}
+
+impl GeometryProvider<Vertex, Index> for ChunkedTerrain {
+    fn geometries(&self) -> Vec<&Geometry<Vertex, Index>> {
+        self.chunks
+            .iter()
+            .filter_map(|c| c.lods.get(c.current_lod))
+            .collect()
+    }
+
+    fn raw_range(&self) -> Option<(f64, f64)> {
+        Some(self.grid.raw_range())
+    }
+
+    fn update(&mut self, vis: &Visualizer) -> error::Result<()> {
+        self.build_lods(vis)?;
+
+        let camera_pos = vis.camera_position();
+        for i in 0..self.chunks.len() {
+            let distance = (self.chunks[i].center - camera_pos).magnitude();
+            self.chunks[i].current_lod = self.select_lod(distance);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,531 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::{self, App, Arg, ArgMatches};
+
+use animated;
+use batch::{BatchJob, Exports};
+use color_ramp::RampPreset;
+use config::{self, Config, ConfigOverrides, InterpolatorKind, LayerConfig, Mode};
+use grid::{NormalMethod, Normalization, Triangulation};
+use record::{Orbit, RecordJob};
+use scene;
+use shader::ShaderMode;
+use share;
+
+/// The configuration given on the command line.
+pub struct Args {
+    pub config: Config,
+    /// The scene file the configuration was loaded from, if any.
+    pub scene_path: Option<PathBuf>,
+    /// The values given as options, which also apply when the scene is reloaded.
+    pub overrides: ConfigOverrides,
+    /// Set to generate terrains without opening a window.
+    pub batch: Option<BatchJob>,
+    /// Set to render an image offscreen instead of opening a window.
+    pub render: Option<RenderJob>,
+    /// Set to record frames offscreen instead of opening a window.
+    pub record: Option<RecordJob>,
+    /// Set to write the procedural textures of the layers to a directory instead of
+    /// opening a window.
+    pub export_textures: Option<PathBuf>,
+}
+
+/// An image of the configured view, rendered without a window.
+pub struct RenderJob {
+    pub path: PathBuf,
+    pub size: (u32, u32),
+}
+
+/// Builds the configuration from the command line arguments.
+///
+/// Prints the help or an error message and exits the process if the arguments
+/// are invalid, like clap does for `--help`.
+pub fn parse_args() -> Args {
+    let matches = build_app().get_matches();
+
+    let overrides = match overrides_from_matches(&matches) {
+        Ok(overrides) => overrides,
+        Err(msg) => exit_with_error(&msg),
+    };
+    let scene_paths: Vec<PathBuf> = matches
+        .values_of("scene")
+        .map(|paths| paths.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    if scene_paths.len() > 1 && !matches.is_present("batch") {
+        exit_with_error("only one scene can be shown at a time; use --batch to generate several");
+    }
+
+    let mut configs = Vec::new();
+    if scene_paths.is_empty() {
+        let mut config = Config::for_mode(overrides.mode.unwrap_or(Mode::Static));
+        overrides.apply(&mut config);
+        match config.validate() {
+            Ok(()) => configs.push((config.mode.to_string(), config)),
+            Err(msg) => exit_with_error(&msg),
+        }
+    }
+    for path in &scene_paths {
+        match scene::load_config(path, &overrides) {
+            Ok(config) => configs.push((file_stem(path), config)),
+            Err(err) => exit_with_error(&err.to_string()),
+        }
+    }
+
+    let batch = matches.value_of("batch").map(|output| BatchJob {
+        output: PathBuf::from(output),
+        seeds: matches
+            .value_of("seeds")
+            .map(|seeds| config::parse_seeds(seeds).unwrap())
+            .unwrap_or_default(),
+        configs: configs.clone(),
+        exports: matches
+            .values_of("exports")
+            .map(parse_exports)
+            .unwrap_or_default(),
+    });
+
+    let config = configs.swap_remove(0).1;
+    let render_size = matches
+        .value_of("render-size")
+        .map(|size| config::parse_size(size).unwrap())
+        .unwrap_or(config.window_size);
+    let render = matches.value_of("render").map(|path| RenderJob {
+        path: PathBuf::from(path),
+        size: render_size,
+    });
+    let record = matches.value_of("record").map(|dir| {
+        let fps: f64 = value_t!(matches, "fps", f64).unwrap_or(30.0);
+        let orbit = if matches.is_present("orbit") {
+            Some(Orbit {
+                radius: value_t!(matches, "orbit-radius", f32).ok(),
+                elevation: value_t!(matches, "orbit-elevation", f32).unwrap_or(30.0),
+                revolutions: value_t!(matches, "revolutions", f32).unwrap_or(1.0),
+            })
+        } else {
+            None
+        };
+        let frames = value_t!(matches, "frames", u32).unwrap_or_else(|_| {
+            // Orbits default to 4 seconds per revolution, animations to one cycle.
+            let duration = match orbit {
+                Some(ref orbit) => 4.0 * f64::from(orbit.revolutions),
+                None => 2.0 * animated::DEFAULT_SWEEP_DURATION,
+            };
+            let duration = value_t!(matches, "duration", f64).unwrap_or(duration);
+            (duration * fps).round().max(1.0) as u32
+        });
+
+        RecordJob {
+            dir: PathBuf::from(dir),
+            size: render_size,
+            fps,
+            frames,
+            gif: matches.value_of("gif").map(PathBuf::from),
+            orbit,
+        }
+    });
+
+    Args {
+        config,
+        scene_path: scene_paths.into_iter().next(),
+        overrides,
+        batch,
+        render,
+        record,
+        export_textures: matches.value_of("export-textures").map(PathBuf::from),
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scene".to_string())
+}
+
+fn parse_exports<'a, I: Iterator<Item = &'a str>>(names: I) -> Exports {
+    let mut exports = Exports::none();
+    for name in names {
+        match name {
+            "heightmap" => exports.heightmap = true,
+            "mesh" => exports.mesh = true,
+            "scene" => exports.scene = true,
+            _ => unreachable!("clap only accepts Exports::NAMES"),
+        }
+    }
+    exports
+}
+
+fn exit_with_error(msg: &str) -> ! {
+    clap::Error::with_description(msg, clap::ErrorKind::InvalidValue).exit()
+}
+
+fn build_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("noise_visualizer")
+        .version(crate_version!())
+        .about("Renders Perlin noise as lit, textured terrain.")
+        .arg(
+            Arg::with_name("scene")
+                .long("scene")
+                .takes_value(true)
+                .value_name("FILE")
+                .multiple(true)
+                .number_of_values(1)
+                .help("TOML scene file to start from, reloaded when it changes; other options override its values"),
+        )
+        .arg(
+            Arg::with_name("batch")
+                .long("batch")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Writes the terrain of each scene and seed to DIR instead of opening a window"),
+        )
+        .arg(
+            Arg::with_name("render")
+                .long("render")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("batch")
+                .help("Renders the view to a PNG image offscreen instead of opening a window"),
+        )
+        .arg(
+            Arg::with_name("render-size")
+                .long("render-size")
+                .takes_value(true)
+                .value_name("WIDTHxHEIGHT")
+                .validator(validate_size)
+                .help("Size of images rendered with --render or --record [default: the window size]"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .value_name("DIR")
+                .conflicts_with_all(&["batch", "render"])
+                .help("Renders the animation at a fixed timestep into numbered PNG files in DIR"),
+        )
+        .arg(
+            Arg::with_name("fps")
+                .long("fps")
+                .takes_value(true)
+                .value_name("RATE")
+                .requires("record")
+                .validator(validate_positive)
+                .help("Frames recorded per second of animation [default: 30]"),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .requires("record")
+                .validator(validate_positive)
+                .help("Seconds of animation to record [default: one cycle of the animation]"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .long("frames")
+                .takes_value(true)
+                .value_name("COUNT")
+                .requires("record")
+                .conflicts_with("duration")
+                .validator(validate_count)
+                .help("Number of frames to record, instead of --duration"),
+        )
+        .arg(
+            Arg::with_name("orbit")
+                .long("orbit")
+                .requires("record")
+                .help("Moves the camera around the center of the terrain while recording"),
+        )
+        .arg(
+            Arg::with_name("orbit-radius")
+                .long("orbit-radius")
+                .takes_value(true)
+                .value_name("DISTANCE")
+                .requires("orbit")
+                .validator(validate_positive)
+                .help("Distance of the camera from the center [default: the terrain diagonal]"),
+        )
+        .arg(
+            Arg::with_name("orbit-elevation")
+                .long("orbit-elevation")
+                .takes_value(true)
+                .value_name("DEGREES")
+                .requires("orbit")
+                .validator(validate_parse::<f32>)
+                .help("Angle of the camera above the terrain [default: 30]"),
+        )
+        .arg(
+            Arg::with_name("revolutions")
+                .long("revolutions")
+                .takes_value(true)
+                .value_name("COUNT")
+                .requires("orbit")
+                .validator(validate_positive)
+                .help("Number of times the camera goes around the terrain [default: 1]"),
+        )
+        .arg(
+            Arg::with_name("gif")
+                .long("gif")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("record")
+                .help("Also writes the recording as a looping animated GIF"),
+        )
+        .arg(
+            Arg::with_name("export-textures")
+                .long("export-textures")
+                .takes_value(true)
+                .value_name("DIR")
+                .conflicts_with_all(&["batch", "render", "record"])
+                .help("Writes the procedural textures of the layers to DIR as PNG files"),
+        )
+        .arg(
+            Arg::with_name("seeds")
+                .long("seeds")
+                .takes_value(true)
+                .value_name("LIST")
+                .requires("batch")
+                .validator(|s| config::parse_seeds(&s).map(|_| ()))
+                .help("Seeds to generate in batch mode, such as 1-100,250 [default: --seed or random]"),
+        )
+        .arg(
+            Arg::with_name("exports")
+                .long("exports")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .requires("batch")
+                .possible_values(Exports::NAMES)
+                .help("Files written for each terrain in batch mode [default: all]"),
+        )
+        .arg(
+            Arg::with_name("share")
+                .long("share")
+                .takes_value(true)
+                .value_name("STRING")
+                .validator(|s| share::decode(&s).map(|_| ()))
+                .help("Reproduces a view shared with the C key; other options override its values"),
+        )
+        .arg(
+            Arg::with_name("mode")
+                .long("mode")
+                .short("m")
+                .takes_value(true)
+                .possible_values(Mode::NAMES)
+                .help("Kind of terrain to show [default: static]"),
+        )
+        .arg(
+            Arg::with_name("dimensions")
+                .long("dimensions")
+                .short("d")
+                .takes_value(true)
+                .value_name("X,Y[,Z]")
+                .validator(|s| config::parse_dimensions(&s).map(|_| ()))
+                .help("Lattice size of the first octave, with Z only in animated mode"),
+        )
+        .arg(
+            Arg::with_name("octaves")
+                .long("octaves")
+                .short("o")
+                .takes_value(true)
+                .value_name("COUNT")
+                .validator(validate_parse::<u32>)
+                .help("Number of octaves"),
+        )
+        .arg(
+            Arg::with_name("persistence")
+                .long("persistence")
+                .short("p")
+                .takes_value(true)
+                .value_name("FACTOR")
+                .validator(validate_positive)
+                .help("Amplitude ratio between successive octaves"),
+        )
+        .arg(
+            Arg::with_name("interpolator")
+                .long("interpolator")
+                .short("i")
+                .takes_value(true)
+                .possible_values(InterpolatorKind::NAMES)
+                .help("Interpolation between lattice points [default: improved]"),
+        )
+        .arg(
+            Arg::with_name("grid")
+                .long("grid")
+                .short("g")
+                .takes_value(true)
+                .value_name("WIDTHxHEIGHT")
+                .validator(|s| config::parse_size(&s).map(|_| ()))
+                .help("Number of samples taken from the noise"),
+        )
+        .arg(
+            Arg::with_name("normalization")
+                .long("normalization")
+                .takes_value(true)
+                .possible_values(Normalization::NAMES)
+                .help("Mapping of noise values to heights [default: minmax]"),
+        )
+        .arg(
+            Arg::with_name("triangulation")
+                .long("triangulation")
+                .takes_value(true)
+                .possible_values(Triangulation::NAMES)
+                .help("How grid cells are split into triangles in static and animated mode and batch meshes [default: uniform]"),
+        )
+        .arg(
+            Arg::with_name("normals")
+                .long("normals")
+                .takes_value(true)
+                .possible_values(NormalMethod::NAMES)
+                .help("How vertex normals are computed in static and animated mode and batch meshes [default: face-average]"),
+        )
+        .arg(
+            Arg::with_name("max-error")
+                .long("max-error")
+                .takes_value(true)
+                .value_name("HEIGHT")
+                .validator(validate_non_negative)
+                .help("Simplifies static terrains and batch meshes to within HEIGHT of the grid values, from 0 to 1"),
+        )
+        .arg(
+            Arg::with_name("assets")
+                .long("assets")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("DIR")
+                .help("Searches DIR for textures that aren't found at their path"),
+        )
+        .arg(
+            Arg::with_name("procedural-textures")
+                .long("procedural-textures")
+                .help("Generates the layer textures from noise instead of loading images"),
+        )
+        .arg(
+            Arg::with_name("ramp")
+                .long("ramp")
+                .takes_value(true)
+                .possible_values(RampPreset::NAMES)
+                .help("Colors the terrain by height instead of with textures"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .short("s")
+                .takes_value(true)
+                .value_name("SEED")
+                .validator(validate_parse::<u64>)
+                .help("Seed for the noise gradients [default: random]"),
+        )
+        .arg(
+            Arg::with_name("shader")
+                .long("shader")
+                .takes_value(true)
+                .possible_values(ShaderMode::NAMES)
+                .help("How the terrain is shaded, cycled with H [default: per-pixel]"),
+        )
+        .arg(
+            Arg::with_name("window")
+                .long("window")
+                .short("w")
+                .takes_value(true)
+                .value_name("WIDTHxHEIGHT")
+                .validator(|s| config::parse_size(&s).map(|_| ()))
+                .help("Window size [default: 1024x768]"),
+        )
+}
+
+fn overrides_from_matches(matches: &ArgMatches) -> Result<ConfigOverrides, String> {
+    let mut overrides = match matches.value_of("share") {
+        Some(s) => share::decode(s)?,
+        None => ConfigOverrides::default(),
+    };
+
+    if let Some(mode) = matches.value_of("mode") {
+        overrides.mode = Some(Mode::from_str(mode)?);
+    }
+    if let Some(dimensions) = matches.value_of("dimensions") {
+        overrides.dimensions = Some(config::parse_dimensions(dimensions)?);
+    }
+    if let Some(octaves) = matches.value_of("octaves") {
+        overrides.octaves = Some(parse_value(octaves)?);
+    }
+    if let Some(persistence) = matches.value_of("persistence") {
+        overrides.persistence = Some(parse_value(persistence)?);
+    }
+    if let Some(interpolator) = matches.value_of("interpolator") {
+        overrides.interpolator = Some(InterpolatorKind::from_str(interpolator)?);
+    }
+    if let Some(grid) = matches.value_of("grid") {
+        overrides.grid_size = Some(config::parse_size(grid)?);
+    }
+    if let Some(normalization) = matches.value_of("normalization") {
+        overrides.normalization = Some(Normalization::from_str(normalization)?);
+    }
+    if let Some(triangulation) = matches.value_of("triangulation") {
+        overrides.triangulation = Some(Triangulation::from_str(triangulation)?);
+    }
+    if let Some(normals) = matches.value_of("normals") {
+        overrides.normals = Some(NormalMethod::from_str(normals)?);
+    }
+    if let Some(max_error) = matches.value_of("max-error") {
+        overrides.max_error = Some(parse_value(max_error)?);
+    }
+    if matches.is_present("procedural-textures") {
+        overrides.layers = Some(LayerConfig::procedural_defaults());
+    }
+    if let Some(dirs) = matches.values_of("assets") {
+        overrides.asset_dirs = dirs.map(PathBuf::from).collect();
+    }
+    if let Some(ramp) = matches.value_of("ramp") {
+        overrides.color_ramp = Some(RampPreset::from_str(ramp)?.ramp());
+    }
+    if let Some(seed) = matches.value_of("seed") {
+        overrides.seed = Some(parse_value(seed)?);
+    }
+    if let Some(shader) = matches.value_of("shader") {
+        overrides.shader_mode = Some(ShaderMode::from_str(shader)?);
+    }
+    if let Some(window) = matches.value_of("window") {
+        overrides.window_size = Some(config::parse_size(window)?);
+    }
+
+    Ok(overrides)
+}
+
+fn parse_value<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid value '{}'", s))
+}
+
+fn validate_parse<T: FromStr>(s: String) -> Result<(), String> {
+    parse_value::<T>(&s).map(|_| ())
+}
+
+fn validate_non_negative(s: String) -> Result<(), String> {
+    match parse_value::<f64>(&s)? {
+        value if value >= 0.0 && value.is_finite() => Ok(()),
+        _ => Err(format!("expected a number of at least 0, got '{}'", s)),
+    }
+}
+
+fn validate_positive(s: String) -> Result<(), String> {
+    match parse_value::<f64>(&s)? {
+        value if value > 0.0 && value.is_finite() => Ok(()),
+        _ => Err(format!("expected a positive number, got '{}'", s)),
+    }
+}
+
+fn validate_count(s: String) -> Result<(), String> {
+    match parse_value::<u32>(&s)? {
+        0 => Err("expected a count of at least 1, got 0".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn validate_size(s: String) -> Result<(), String> {
+    match config::parse_size(&s)? {
+        (width, height) if width > 0 && height > 0 => Ok(()),
+        _ => Err(format!("expected a size greater than 0, got '{}'", s)),
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A color at a height of the terrain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorStop {
+    /// From 0 at the lowest point of the terrain to 1 at the highest.
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+/// Colors for the terrain by height, linearly interpolated between stops.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    name: String,
+    stops: Vec<ColorStop>,
+}
+
+impl ColorRamp {
+    /// Fails unless there is at least one stop and the heights are ascending and
+    /// between 0 and 1.
+    pub fn new(name: &str, stops: Vec<ColorStop>) -> Result<ColorRamp, String> {
+        if stops.is_empty() {
+            return Err("a color ramp needs at least one stop".to_string());
+        }
+        if let Some(stop) = stops
+            .iter()
+            .find(|stop| !(stop.height >= 0.0 && stop.height <= 1.0))
+        {
+            return Err(format!(
+                "color stop heights must be between 0 and 1, got {}",
+                stop.height
+            ));
+        }
+        if stops.windows(2).any(|pair| pair[0].height > pair[1].height) {
+            return Err("color stops must be sorted by height".to_string());
+        }
+
+        Ok(ColorRamp {
+            name: name.to_string(),
+            stops,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// The color at `height`, which is clamped to the first and last stops.
+    pub fn color_at(&self, height: f32) -> [f32; 4] {
+        let first = &self.stops[0];
+        if height <= first.height {
+            return first.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (lower, upper) = (&pair[0], &pair[1]);
+            if height <= upper.height {
+                let t = (height - lower.height) / (upper.height - lower.height);
+                let mut color = [0.0; 4];
+                for i in 0..4 {
+                    color[i] = lower.color[i] + (upper.color[i] - lower.color[i]) * t;
+                }
+                return color;
+            }
+        }
+        self.stops[self.stops.len() - 1].color
+    }
+
+    /// Samples the ramp at `resolution` evenly spaced heights from 0 to 1, as the
+    /// texels of a 1D texture.
+    pub fn bake(&self, resolution: usize) -> Vec<(f32, f32, f32, f32)> {
+        assert!(resolution >= 2);
+        (0..resolution)
+            .map(|i| {
+                let c = self.color_at(i as f32 / (resolution - 1) as f32);
+                (c[0], c[1], c[2], c[3])
+            })
+            .collect()
+    }
+}
+
+/// The color ramps that come with the visualizer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RampPreset {
+    /// Water, grass, dirt and stone.
+    Terrain,
+    /// Blue through green to red.
+    Spectrum,
+    Desert,
+    Arctic,
+    /// Perceptually uniform from dark blue to yellow, and readable with color blindness.
+    Viridis,
+    /// Perceptually uniform from black through purple to pale yellow.
+    Magma,
+    /// Like viridis, but also close to uniform for red-green color blindness.
+    Cividis,
+    Grayscale,
+    /// Blue through gray to red, for values on either side of the middle.
+    Diverging,
+}
+
+impl RampPreset {
+    pub const NAMES: &'static [&'static str] = &[
+        "terrain",
+        "spectrum",
+        "desert",
+        "arctic",
+        "viridis",
+        "magma",
+        "cividis",
+        "grayscale",
+        "diverging",
+    ];
+    pub const ALL: &'static [RampPreset] = &[
+        RampPreset::Terrain,
+        RampPreset::Spectrum,
+        RampPreset::Desert,
+        RampPreset::Arctic,
+        RampPreset::Viridis,
+        RampPreset::Magma,
+        RampPreset::Cividis,
+        RampPreset::Grayscale,
+        RampPreset::Diverging,
+    ];
+
+    pub fn ramp(&self) -> ColorRamp {
+        let stops: &[(f32, [f32; 4])] = match *self {
+            RampPreset::Terrain => &[
+                (0.15, [0.1, 0.1, 0.6, 1.0]),
+                (0.30, [0.1, 0.6, 0.1, 1.0]),
+                (0.45, [0.1, 0.6, 0.1, 1.0]),
+                (0.55, [0.910, 0.435, 0.220, 1.0]),
+                (0.70, [0.910, 0.435, 0.220, 1.0]),
+                (0.90, [0.5, 0.5, 0.5, 1.0]),
+            ],
+            RampPreset::Spectrum => &[
+                (0.0, [0.0, 0.0, 1.0, 1.0]),
+                (0.5, [0.0, 1.0, 0.0, 1.0]),
+                (1.0, [1.0, 0.0, 0.0, 1.0]),
+            ],
+            RampPreset::Desert => &[
+                (0.10, [0.55, 0.35, 0.20, 1.0]),
+                (0.40, [0.87, 0.72, 0.48, 1.0]),
+                (0.75, [0.93, 0.84, 0.65, 1.0]),
+                (1.0, [0.70, 0.45, 0.30, 1.0]),
+            ],
+            RampPreset::Arctic => &[
+                (0.20, [0.05, 0.15, 0.35, 1.0]),
+                (0.30, [0.55, 0.75, 0.85, 1.0]),
+                (0.50, [0.85, 0.92, 0.96, 1.0]),
+                (1.0, [1.0, 1.0, 1.0, 1.0]),
+            ],
+            // The scientific colormaps are sampled from matplotlib's at every eighth.
+            RampPreset::Viridis => &[
+                (0.0, [0.267, 0.005, 0.329, 1.0]),
+                (0.125, [0.283, 0.141, 0.458, 1.0]),
+                (0.25, [0.254, 0.265, 0.530, 1.0]),
+                (0.375, [0.192, 0.408, 0.556, 1.0]),
+                (0.5, [0.149, 0.510, 0.557, 1.0]),
+                (0.625, [0.122, 0.619, 0.537, 1.0]),
+                (0.75, [0.208, 0.718, 0.473, 1.0]),
+                (0.875, [0.431, 0.808, 0.345, 1.0]),
+                (1.0, [0.993, 0.906, 0.144, 1.0]),
+            ],
+            RampPreset::Magma => &[
+                (0.0, [0.001, 0.000, 0.014, 1.0]),
+                (0.125, [0.110, 0.063, 0.267, 1.0]),
+                (0.25, [0.310, 0.071, 0.482, 1.0]),
+                (0.375, [0.506, 0.145, 0.506, 1.0]),
+                (0.5, [0.710, 0.212, 0.478, 1.0]),
+                (0.625, [0.898, 0.314, 0.392, 1.0]),
+                (0.75, [0.984, 0.529, 0.380, 1.0]),
+                (0.875, [0.996, 0.761, 0.529, 1.0]),
+                (1.0, [0.987, 0.991, 0.750, 1.0]),
+            ],
+            RampPreset::Cividis => &[
+                (0.0, [0.000, 0.135, 0.305, 1.0]),
+                (0.125, [0.070, 0.208, 0.439, 1.0]),
+                (0.25, [0.231, 0.286, 0.424, 1.0]),
+                (0.375, [0.341, 0.365, 0.427, 1.0]),
+                (0.5, [0.439, 0.443, 0.451, 1.0]),
+                (0.625, [0.541, 0.525, 0.471, 1.0]),
+                (0.75, [0.647, 0.612, 0.455, 1.0]),
+                (0.875, [0.765, 0.702, 0.412, 1.0]),
+                (1.0, [0.996, 0.910, 0.220, 1.0]),
+            ],
+            RampPreset::Grayscale => &[
+                (0.0, [0.0, 0.0, 0.0, 1.0]),
+                (1.0, [1.0, 1.0, 1.0, 1.0]),
+            ],
+            // Moreland's cool to warm map.
+            RampPreset::Diverging => &[
+                (0.0, [0.230, 0.299, 0.754, 1.0]),
+                (0.25, [0.552, 0.690, 0.996, 1.0]),
+                (0.5, [0.865, 0.865, 0.865, 1.0]),
+                (0.75, [0.958, 0.604, 0.482, 1.0]),
+                (1.0, [0.706, 0.016, 0.150, 1.0]),
+            ],
+        };
+
+        let stops = stops
+            .iter()
+            .map(|&(height, color)| ColorStop { height, color })
+            .collect();
+        ColorRamp::new(&self.to_string(), stops).unwrap()
+    }
+}
+
+impl FromStr for RampPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RampPreset, String> {
+        match s {
+            "terrain" => Ok(RampPreset::Terrain),
+            "spectrum" => Ok(RampPreset::Spectrum),
+            "desert" => Ok(RampPreset::Desert),
+            "arctic" => Ok(RampPreset::Arctic),
+            "viridis" => Ok(RampPreset::Viridis),
+            "magma" => Ok(RampPreset::Magma),
+            "cividis" => Ok(RampPreset::Cividis),
+            "grayscale" => Ok(RampPreset::Grayscale),
+            "diverging" => Ok(RampPreset::Diverging),
+            _ => Err(format!("unknown color ramp '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RampPreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            RampPreset::Terrain => "terrain",
+            RampPreset::Spectrum => "spectrum",
+            RampPreset::Desert => "desert",
+            RampPreset::Arctic => "arctic",
+            RampPreset::Viridis => "viridis",
+            RampPreset::Magma => "magma",
+            RampPreset::Cividis => "cividis",
+            RampPreset::Grayscale => "grayscale",
+            RampPreset::Diverging => "diverging",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(height: f32, gray: f32) -> ColorStop {
+        ColorStop {
+            height,
+            color: [gray, gray, gray, 1.0],
+        }
+    }
+
+    #[test]
+    fn invalid_stops_are_rejected() {
+        assert!(ColorRamp::new("empty", vec![]).is_err());
+        assert!(ColorRamp::new("low", vec![stop(-0.1, 0.0)]).is_err());
+        assert!(ColorRamp::new("high", vec![stop(1.5, 0.0)]).is_err());
+        assert!(ColorRamp::new("nan", vec![stop(::std::f32::NAN, 0.0)]).is_err());
+        assert!(ColorRamp::new("unsorted", vec![stop(0.8, 0.0), stop(0.2, 1.0)]).is_err());
+        assert!(ColorRamp::new("equal", vec![stop(0.5, 0.0), stop(0.5, 1.0)]).is_ok());
+    }
+
+    #[test]
+    fn color_at_interpolates_between_stops() {
+        let ramp = ColorRamp::new("ramp", vec![stop(0.2, 0.0), stop(0.6, 1.0)]).unwrap();
+        assert_eq!(ramp.color_at(0.2), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(ramp.color_at(0.6), [1.0, 1.0, 1.0, 1.0]);
+        assert!((ramp.color_at(0.3)[0] - 0.25).abs() < 1e-6);
+        assert!((ramp.color_at(0.5)[0] - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn color_at_clamps_to_the_first_and_last_stops() {
+        let ramp = ColorRamp::new("ramp", vec![stop(0.2, 0.1), stop(0.6, 0.9)]).unwrap();
+        assert_eq!(ramp.color_at(0.0), ramp.color_at(0.2));
+        assert_eq!(ramp.color_at(1.0), ramp.color_at(0.6));
+    }
+
+    #[test]
+    fn single_stop_is_a_constant_color() {
+        let ramp = ColorRamp::new("single", vec![stop(0.5, 0.4)]).unwrap();
+        assert_eq!(ramp.color_at(0.0), [0.4, 0.4, 0.4, 1.0]);
+        assert_eq!(ramp.color_at(1.0), [0.4, 0.4, 0.4, 1.0]);
+        assert_eq!(ramp.bake(4).len(), 4);
+    }
+
+    #[test]
+    fn presets_are_valid_and_named() {
+        for preset in RampPreset::ALL {
+            assert_eq!(preset.ramp().name(), preset.to_string());
+            assert_eq!(preset.to_string().parse::<RampPreset>(), Ok(*preset));
+        }
+    }
+}
@@ -0,0 +1,730 @@
+use std::f32;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use cgmath::{Matrix4, Vector3};
+
+use color_ramp::ColorRamp;
+use grid::{MeshOptions, NormalMethod, Normalization, Shading, Triangulation};
+use procedural::{ProceduralTexture, TexturePreset};
+use shader::ShaderMode;
+use visualizer::{MAX_LAYERS, MAX_MATERIALS};
+
+/// Which kind of terrain is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// A single grid sampled from 2D noise.
+    Static,
+    /// A grid sampled from a 2D slice moving through 3D noise.
+    Animated,
+    /// A large 2D noise grid split into tiles with levels of detail.
+    Chunked,
+    /// Unbounded 2D noise, generated in chunks around the camera.
+    Streaming,
+}
+
+impl Mode {
+    pub const NAMES: &'static [&'static str] = &["static", "animated", "chunked", "streaming"];
+
+    /// The number of dimensions of the noise used by this mode.
+    pub fn noise_dimensions(&self) -> usize {
+        match *self {
+            Mode::Animated => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "static" => Ok(Mode::Static),
+            "animated" => Ok(Mode::Animated),
+            "chunked" => Ok(Mode::Chunked),
+            "streaming" => Ok(Mode::Streaming),
+            _ => Err(format!("unknown mode '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Mode::Static => "static",
+            Mode::Animated => "animated",
+            Mode::Chunked => "chunked",
+            Mode::Streaming => "streaming",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The interpolation used between lattice points of the Perlin noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpolatorKind {
+    Linear,
+    Cubic,
+    ImprovedPerlin,
+}
+
+impl InterpolatorKind {
+    pub const NAMES: &'static [&'static str] = &["linear", "cubic", "improved"];
+}
+
+impl FromStr for InterpolatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<InterpolatorKind, String> {
+        match s {
+            "linear" => Ok(InterpolatorKind::Linear),
+            "cubic" => Ok(InterpolatorKind::Cubic),
+            "improved" => Ok(InterpolatorKind::ImprovedPerlin),
+            _ => Err(format!("unknown interpolator '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for InterpolatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            InterpolatorKind::Linear => "linear",
+            InterpolatorKind::Cubic => "cubic",
+            InterpolatorKind::ImprovedPerlin => "improved",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseConfig {
+    /// Lattice size of the first octave, one entry per noise dimension.
+    pub dimensions: Vec<u32>,
+    pub octaves: u32,
+    pub persistence: f64,
+    pub interpolator: InterpolatorKind,
+    /// Seed for the gradient generator, or `None` for a random one. The same seed
+    /// always gives the same terrain.
+    pub seed: Option<u64>,
+}
+
+/// Maps grid coordinates, with the noise value as z, into the world.
+///
+/// In animated mode the z scale is relative to half the grid width, see
+/// `Config::model_matrix`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelTransform {
+    pub translation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl ModelTransform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let scale = self.scale;
+        Matrix4::from_translation(Vector3::from(self.translation))
+            * Matrix4::from_nonuniform_scale(scale[0], scale[1], scale[2])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialConfig {
+    pub ambient: [f32; 4],
+    pub diffuse: [f32; 4],
+    pub specular: [f32; 4],
+    pub shine: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightConfig {
+    pub direction: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl Default for LightConfig {
+    fn default() -> LightConfig {
+        LightConfig {
+            direction: [100.0, 0.0, -500.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Where the image of a texture layer comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureSource {
+    File(PathBuf),
+    Procedural(ProceduralTexture),
+}
+
+impl From<PathBuf> for TextureSource {
+    fn from(path: PathBuf) -> TextureSource {
+        TextureSource::File(path)
+    }
+}
+
+impl<'a> From<&'a str> for TextureSource {
+    fn from(path: &'a str) -> TextureSource {
+        TextureSource::File(PathBuf::from(path))
+    }
+}
+
+impl From<ProceduralTexture> for TextureSource {
+    fn from(texture: ProceduralTexture) -> TextureSource {
+        TextureSource::Procedural(texture)
+    }
+}
+
+impl fmt::Display for TextureSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TextureSource::File(ref path) => write!(f, "{}", path.display()),
+            TextureSource::Procedural(ref texture) => write!(f, "procedural {}", texture.name),
+        }
+    }
+}
+
+/// A texture painted over the terrain where its height and slope are in range.
+///
+/// Layers are painted in order, so each one covers the ones before it; the first
+/// layer is the base and is drawn everywhere.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerConfig {
+    pub texture: TextureSource,
+    /// The heights covered by the layer, from 0 at the lowest point of the terrain to
+    /// 1 at the highest.
+    pub height: [f32; 2],
+    /// The slopes covered by the layer, in degrees from flat.
+    pub slope: [f32; 2],
+    /// The number of times the texture repeats over a unit of texture coordinates.
+    pub scale: f32,
+    /// The height over which the layer fades in and out at the ends of its range.
+    pub blend: f32,
+}
+
+impl LayerConfig {
+    /// A layer covering the whole terrain.
+    pub fn new<T: Into<TextureSource>>(texture: T) -> LayerConfig {
+        LayerConfig {
+            texture: texture.into(),
+            height: [0.0, 1.0],
+            slope: [0.0, 90.0],
+            scale: 4.0,
+            blend: 0.0,
+        }
+    }
+
+    /// Water, covered by grass, then dirt and stone as the terrain rises, and rock on
+    /// steep slopes at any height.
+    pub fn defaults() -> Vec<LayerConfig> {
+        let layer = |texture: &str, min_height: f32, blend: f32| LayerConfig {
+            height: [min_height, 1.0],
+            blend,
+            ..LayerConfig::new(texture)
+        };
+        vec![
+            LayerConfig::new(TexturePreset::Water.texture()),
+            layer("./Assets/grass_texture_1.jpg", 0.30, 0.10),
+            layer("./Assets/Orange dirtl texture-1.jpg", 0.65, 0.15),
+            layer("./Assets/stone_texture_1.jpg", 0.85, 0.15),
+            LayerConfig {
+                slope: [40.0, 90.0],
+                ..LayerConfig::new("./Assets/stone_texture_1.jpg")
+            },
+        ]
+    }
+
+    /// Like `defaults`, with textures generated from noise instead of loaded: sand,
+    /// covered by grass, then rock and snow, and rock on steep slopes.
+    pub fn procedural_defaults() -> Vec<LayerConfig> {
+        let layer = |preset: TexturePreset, min_height: f32, blend: f32| LayerConfig {
+            height: [min_height, 1.0],
+            blend,
+            ..LayerConfig::new(preset.texture())
+        };
+        vec![
+            layer(TexturePreset::Sand, 0.0, 0.0),
+            layer(TexturePreset::Grass, 0.30, 0.10),
+            layer(TexturePreset::Rock, 0.65, 0.15),
+            layer(TexturePreset::Snow, 0.85, 0.15),
+            LayerConfig {
+                slope: [40.0, 90.0],
+                ..LayerConfig::new(TexturePreset::Rock.texture())
+            },
+        ]
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let TextureSource::Procedural(ref texture) = self.texture {
+            texture
+                .validate()
+                .map_err(|err| format!("layer {}: {}", self.texture, err))?;
+        }
+        let height = self.height;
+        if !(height[0] >= 0.0 && height[0] <= height[1] && height[1] <= 1.0) {
+            return Err(format!(
+                "layer {}: heights must be an increasing range from 0 to 1",
+                self.texture
+            ));
+        }
+        if self.slope[0] > self.slope[1] || self.slope[0] < 0.0 || self.slope[1] > 90.0 {
+            return Err(format!(
+                "layer {}: slopes must be an increasing range from 0 to 90 degrees",
+                self.texture
+            ));
+        }
+        if !(self.scale > 0.0) {
+            return Err(format!("layer {}: the scale must be greater than 0", self.texture));
+        }
+        if !(self.blend >= 0.0) {
+            return Err(format!("layer {}: the blend must not be negative", self.texture));
+        }
+        Ok(())
+    }
+}
+
+/// The starting pose of the camera, with angles as in `CameraController`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraConfig {
+    pub position: [f32; 3],
+    pub theta: f32,
+    pub phi: f32,
+    pub fov: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> CameraConfig {
+        CameraConfig {
+            position: [0.0, 0.0, 0.0],
+            theta: f32::consts::PI / 2.0,
+            phi: f32::consts::PI / 2.0,
+            fov: 45.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    pub noise: NoiseConfig,
+    pub grid_size: (u32, u32),
+    pub normalization: Normalization,
+    /// How grid cells are split into triangles and how vertex normals are computed, in
+    /// static and animated mode and for meshes exported in batch mode. Chunked and
+    /// streaming terrains build their tiles with uniform triangles and
+    /// central-difference normals, so that neighbouring tiles match.
+    pub mesh: MeshOptions,
+    /// Simplifies the mesh of static terrains so that it deviates from the grid by at
+    /// most this much vertically; the other modes always show the full grid. Meshes
+    /// exported in batch mode are simplified whatever the mode.
+    pub max_error: Option<f64>,
+    pub model: ModelTransform,
+    pub materials: Vec<MaterialConfig>,
+    pub light: LightConfig,
+    pub layers: Vec<LayerConfig>,
+    /// Directories searched in order for textures that aren't found at their path.
+    pub asset_dirs: Vec<PathBuf>,
+    /// Colors the terrain by height instead of with its textures.
+    pub color_ramp: Option<ColorRamp>,
+    pub camera: CameraConfig,
+    pub shading: Shading,
+    pub shader_mode: ShaderMode,
+    pub wireframe: bool,
+    /// Projects the layer textures along all three axes instead of only from above,
+    /// so they aren't stretched over steep slopes.
+    pub triplanar: bool,
+    /// Limits layers to their ranges of slopes; otherwise the layers that have one
+    /// are left out.
+    pub slope_layers: bool,
+    pub window_size: (u32, u32),
+}
+
+impl Config {
+    /// The default configuration for `mode`.
+    pub fn for_mode(mode: Mode) -> Config {
+        let (dimensions, octaves, persistence, grid_size) = match mode {
+            Mode::Static => (vec![2, 2], 10, 3.0, (200, 200)),
+            Mode::Animated => (vec![2, 2, 3], 8, 2.5, (150, 150)),
+            Mode::Chunked => (vec![4, 4], 10, 3.0, (1025, 1025)),
+            Mode::Streaming => (vec![2, 2], 10, 3.0, (200, 200)),
+        };
+        let model = match mode {
+            Mode::Static => ModelTransform {
+                translation: [-50.0, -50.0, 20.0],
+                scale: [0.3333, 0.3333, 33.33],
+            },
+            Mode::Animated => ModelTransform {
+                translation: [0.0, 0.0, 20.0],
+                scale: [1.0, 1.0, 1.0],
+            },
+            Mode::Chunked => ModelTransform {
+                translation: [-50.0, -50.0, 20.0],
+                scale: [0.1, 0.1, 30.0],
+            },
+            Mode::Streaming => ModelTransform {
+                translation: [0.0, 0.0, 20.0],
+                scale: [0.3333, 0.3333, 33.33],
+            },
+        };
+
+        Config {
+            mode,
+            noise: NoiseConfig {
+                dimensions,
+                octaves,
+                persistence,
+                interpolator: InterpolatorKind::ImprovedPerlin,
+                seed: None,
+            },
+            grid_size,
+            normalization: Normalization::default(),
+            mesh: MeshOptions::default(),
+            max_error: None,
+            model,
+            materials: vec![
+                MaterialConfig {
+                    ambient: [0.2, 0.2, 0.2, 1.0],
+                    diffuse: [1.0, 1.0, 1.0, 1.0],
+                    specular: [0.0, 0.0, 0.0, 1.0],
+                    shine: 120.0,
+                },
+                MaterialConfig {
+                    ambient: [0.2, 0.2, 0.2, 1.0],
+                    diffuse: [1.0, 1.0, 1.0, 1.0],
+                    specular: [1.0, 1.0, 1.0, 1.0],
+                    shine: 120.0,
+                },
+            ],
+            light: LightConfig::default(),
+            layers: LayerConfig::defaults(),
+            asset_dirs: Vec::new(),
+            color_ramp: None,
+            camera: CameraConfig::default(),
+            shading: Shading::default(),
+            shader_mode: ShaderMode::default(),
+            wireframe: false,
+            triplanar: true,
+            slope_layers: true,
+            window_size: (1024, 768),
+        }
+    }
+
+    /// The transform of the terrain into the world.
+    ///
+    /// In animated mode heights are also scaled by half the grid width, so they stay
+    /// in proportion to the width of the terrain whatever the grid size.
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        match self.mode {
+            Mode::Animated => {
+                let height_scale = self.grid_size.0 as f32 / 2.0;
+                self.model.matrix() * Matrix4::from_nonuniform_scale(1.0, 1.0, height_scale)
+            }
+            _ => self.model.matrix(),
+        }
+    }
+
+    /// Checks the values that can't be validated independently of each other.
+    pub fn validate(&self) -> Result<(), String> {
+        let expected = self.mode.noise_dimensions();
+        if self.noise.dimensions.len() != expected {
+            return Err(format!(
+                "{} mode needs {} noise dimensions, got {}",
+                self.mode,
+                expected,
+                self.noise.dimensions.len()
+            ));
+        }
+        if self.noise.dimensions.iter().any(|&d| d == 0) {
+            return Err("noise dimensions must be greater than 0".to_string());
+        }
+        if self.noise.octaves == 0 {
+            return Err("the number of octaves must be greater than 0".to_string());
+        }
+        if !(self.noise.persistence > 0.0 && self.noise.persistence.is_finite()) {
+            return Err(format!(
+                "the persistence must be a positive number, got {}",
+                self.noise.persistence
+            ));
+        }
+        if self.grid_size.0 < 2 || self.grid_size.1 < 2 {
+            return Err("the grid must be at least 2x2".to_string());
+        }
+        if self.grid_size.0 > MAX_GRID_SIZE || self.grid_size.1 > MAX_GRID_SIZE {
+            return Err(format!(
+                "the grid can be at most {0}x{0}, got {1}x{2}",
+                MAX_GRID_SIZE, self.grid_size.0, self.grid_size.1
+            ));
+        }
+        if self.window_size.0 == 0 || self.window_size.1 == 0 {
+            return Err("the window size must be greater than 0".to_string());
+        }
+        // A zero scale flattens the terrain into a plane, and its normals can't be
+        // transformed.
+        let scale = self.model.scale;
+        if scale.iter().any(|&s| !(s.is_finite() && s != 0.0)) {
+            return Err(format!(
+                "the model scale must be finite and not 0, got {:?}",
+                scale
+            ));
+        }
+        if let Some(max_error) = self.max_error {
+            if !(max_error >= 0.0 && max_error.is_finite()) {
+                return Err(format!(
+                    "the maximum error must be at least 0, got {}",
+                    max_error
+                ));
+            }
+        }
+        if self.materials.is_empty() || self.materials.len() > MAX_MATERIALS {
+            return Err(format!(
+                "between 1 and {} materials are needed, got {}",
+                MAX_MATERIALS,
+                self.materials.len()
+            ));
+        }
+        if self.layers.is_empty() || self.layers.len() > MAX_LAYERS {
+            return Err(format!(
+                "between 1 and {} texture layers are needed, got {}",
+                MAX_LAYERS,
+                self.layers.len()
+            ));
+        }
+        for layer in &self.layers {
+            layer.validate()?;
+        }
+        if self.light.direction == [0.0, 0.0, 0.0] {
+            return Err("the light direction must not be zero".to_string());
+        }
+        if !(self.camera.fov > 0.0 && self.camera.fov < 180.0) {
+            return Err(format!(
+                "the camera field of view must be between 0 and 180 degrees, got {}",
+                self.camera.fov
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::for_mode(Mode::Static)
+    }
+}
+
+/// Values set explicitly, such as on the command line, which take precedence over
+/// both the defaults and scene files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigOverrides {
+    pub mode: Option<Mode>,
+    pub dimensions: Option<Vec<u32>>,
+    pub octaves: Option<u32>,
+    pub persistence: Option<f64>,
+    pub interpolator: Option<InterpolatorKind>,
+    pub grid_size: Option<(u32, u32)>,
+    pub normalization: Option<Normalization>,
+    pub triangulation: Option<Triangulation>,
+    pub normals: Option<NormalMethod>,
+    pub max_error: Option<f64>,
+    pub seed: Option<u64>,
+    pub layers: Option<Vec<LayerConfig>>,
+    /// Searched before the asset directories of the config.
+    pub asset_dirs: Vec<PathBuf>,
+    pub color_ramp: Option<ColorRamp>,
+    pub camera: Option<CameraConfig>,
+    pub shading: Option<Shading>,
+    pub shader_mode: Option<ShaderMode>,
+    pub window_size: Option<(u32, u32)>,
+}
+
+impl ConfigOverrides {
+    /// Replaces the values of `config` that have an override, except for the mode,
+    /// which has to be picked before creating the config.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(ref dimensions) = self.dimensions {
+            config.noise.dimensions = dimensions.clone();
+        }
+        if let Some(octaves) = self.octaves {
+            config.noise.octaves = octaves;
+        }
+        if let Some(persistence) = self.persistence {
+            config.noise.persistence = persistence;
+        }
+        if let Some(interpolator) = self.interpolator {
+            config.noise.interpolator = interpolator;
+        }
+        if let Some(grid_size) = self.grid_size {
+            config.grid_size = grid_size;
+        }
+        if let Some(normalization) = self.normalization {
+            config.normalization = normalization;
+        }
+        if let Some(triangulation) = self.triangulation {
+            config.mesh.triangulation = triangulation;
+        }
+        if let Some(normals) = self.normals {
+            config.mesh.normals = normals;
+        }
+        if self.max_error.is_some() {
+            config.max_error = self.max_error;
+        }
+        if self.seed.is_some() {
+            config.noise.seed = self.seed;
+        }
+        if let Some(ref layers) = self.layers {
+            config.layers = layers.clone();
+        }
+        if !self.asset_dirs.is_empty() {
+            let mut dirs = self.asset_dirs.clone();
+            dirs.extend(config.asset_dirs.drain(..));
+            config.asset_dirs = dirs;
+        }
+        if self.color_ramp.is_some() {
+            config.color_ramp = self.color_ramp.clone();
+        }
+        if let Some(ref camera) = self.camera {
+            config.camera = camera.clone();
+        }
+        if let Some(shading) = self.shading {
+            config.shading = shading;
+        }
+        if let Some(shader_mode) = self.shader_mode {
+            config.shader_mode = shader_mode;
+        }
+        if let Some(window_size) = self.window_size {
+            config.window_size = window_size;
+        }
+    }
+}
+
+/// Parses a `WIDTHxHEIGHT` pair, such as `1024x768`.
+pub fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let parts: Vec<&str> = s.split('x').collect();
+    if parts.len() != 2 {
+        return Err(format!("expected WIDTHxHEIGHT, got '{}'", s));
+    }
+
+    let width = parts[0]
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid width '{}'", parts[0]))?;
+    let height = parts[1]
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid height '{}'", parts[1]))?;
+    Ok((width, height))
+}
+
+/// Parses a comma separated list of lattice sizes, such as `2,2,3`.
+pub fn parse_dimensions(s: &str) -> Result<Vec<u32>, String> {
+    s.split(',')
+        .map(|d| {
+            d.trim()
+                .parse()
+                .map_err(|_| format!("invalid dimension '{}'", d))
+        })
+        .collect()
+}
+
+/// The most points a grid can have along either side.
+pub const MAX_GRID_SIZE: u32 = 8193;
+
+/// The most seeds a list given to `parse_seeds` can expand to.
+pub const MAX_SEEDS: usize = 100_000;
+
+/// Parses a comma separated list of seeds and inclusive ranges, such as `1-10,42`.
+pub fn parse_seeds(s: &str) -> Result<Vec<u64>, String> {
+    let mut seeds = Vec::new();
+    for part in s.split(',') {
+        let part = part.trim();
+        let parse = |seed: &str| {
+            seed.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid seed '{}'", seed))
+        };
+
+        match part.find('-') {
+            Some(dash) => {
+                let (first, last) = (parse(&part[..dash])?, parse(&part[dash + 1..])?);
+                if first > last {
+                    return Err(format!("invalid seed range '{}'", part));
+                }
+                if last - first >= (MAX_SEEDS - seeds.len()) as u64 {
+                    return Err(format!("too many seeds, at most {} are allowed", MAX_SEEDS));
+                }
+                seeds.extend(first..=last);
+            }
+            None => seeds.push(parse(part)?),
+        }
+        if seeds.len() > MAX_SEEDS {
+            return Err(format!("too many seeds, at most {} are allowed", MAX_SEEDS));
+        }
+    }
+    Ok(seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_and_ranges_are_expanded() {
+        assert_eq!(parse_seeds("42"), Ok(vec![42]));
+        assert_eq!(parse_seeds("1-3, 7 ,5-5"), Ok(vec![1, 2, 3, 7, 5]));
+    }
+
+    #[test]
+    fn ranges_can_end_at_the_largest_seed() {
+        assert_eq!(
+            parse_seeds("18446744073709551614-18446744073709551615"),
+            Ok(vec![u64::max_value() - 1, u64::max_value()])
+        );
+    }
+
+    #[test]
+    fn invalid_seeds_are_rejected() {
+        assert!(parse_seeds("").is_err());
+        assert!(parse_seeds("1,,2").is_err());
+        assert!(parse_seeds("abc").is_err());
+        assert!(parse_seeds("-1").is_err());
+        assert!(parse_seeds("5-3").is_err());
+        assert!(parse_seeds("18446744073709551616").is_err());
+    }
+
+    #[test]
+    fn too_many_seeds_are_rejected() {
+        let max = MAX_SEEDS as u64;
+        assert_eq!(parse_seeds(&format!("1-{}", max)).unwrap().len(), MAX_SEEDS);
+        assert!(parse_seeds(&format!("1-{}", max + 1)).is_err());
+        assert!(parse_seeds(&format!("1-{},0", max)).is_err());
+        assert!(parse_seeds(&format!("0,1-{}", max)).is_err());
+        assert!(parse_seeds("0-18446744073709551615").is_err());
+    }
+
+    #[test]
+    fn zero_or_infinite_model_scale_is_rejected() {
+        let mut config = Config::default();
+        assert_eq!(config.validate(), Ok(()));
+        config.model.scale = [1.0, 1.0, 0.0];
+        assert!(config.validate().is_err());
+        config.model.scale = [1.0, f32::INFINITY, 1.0];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn persistence_and_layer_heights_are_checked() {
+        let mut config = Config::default();
+        config.noise.persistence = ::std::f64::NAN;
+        assert!(config.validate().is_err());
+        config.noise.persistence = -2.0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.layers[1].height = [0.5, 1.5];
+        assert!(config.validate().is_err());
+        config.layers[1].height = [-0.5, 1.0];
+        assert!(config.validate().is_err());
+        config.layers[1].height = [0.6, 0.4];
+        assert!(config.validate().is_err());
+    }
+}
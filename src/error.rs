@@ -0,0 +1,87 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::result;
+
+use glium;
+
+/// The ways showing, rendering or exporting a terrain can fail.
+#[derive(Debug)]
+pub enum Error {
+    /// The window or the OpenGL context couldn't be created.
+    Context(Box<error::Error>),
+    /// The program built from the named shaders failed to compile or link.
+    Shader(&'static str, glium::program::ProgramChooserCreationError),
+    /// The described buffer, texture or framebuffer couldn't be created.
+    Resource(&'static str, Box<error::Error>),
+    /// Drawing the named part of the view failed.
+    Draw(&'static str, glium::DrawError),
+    /// The frame couldn't be shown in the window.
+    SwapBuffers(glium::SwapBuffersError),
+    /// The window couldn't do what was asked, as described.
+    Window(&'static str),
+    Io(PathBuf, io::Error),
+    /// What was asked for can't be done, for the reason given.
+    Invalid(String),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl Error {
+    pub fn context<E: error::Error + 'static>(err: E) -> Error {
+        Error::Context(Box::new(err))
+    }
+
+    pub fn resource<E: error::Error + 'static>(what: &'static str, err: E) -> Error {
+        Error::Resource(what, Box::new(err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Context(ref err) => write!(
+                f,
+                "could not create an OpenGL 3.3 context, which the visualizer needs: {}",
+                err
+            ),
+            Error::Shader(name, ref err) => {
+                write!(f, "could not build the {} shaders: {}", name, err)
+            }
+            Error::Resource(what, ref err) => write!(f, "could not create {}: {}", what, err),
+            Error::Draw(what, ref err) => write!(f, "could not draw {}: {}", what, err),
+            Error::SwapBuffers(ref err) => write!(f, "could not show the frame: {}", err),
+            Error::Window(msg) => f.write_str(msg),
+            Error::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            Error::Invalid(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Context(..) => "could not create the OpenGL context",
+            Error::Shader(..) => "could not build shaders",
+            Error::Resource(..) => "could not create an OpenGL resource",
+            Error::Draw(..) => "could not draw",
+            Error::SwapBuffers(..) => "could not show the frame",
+            Error::Window(msg) => msg,
+            Error::Io(..) => "could not read or write a file",
+            Error::Invalid(ref msg) => msg,
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Context(ref err) => Some(&**err),
+            Error::Shader(_, ref err) => Some(err),
+            Error::Resource(_, ref err) => Some(&**err),
+            Error::Draw(_, ref err) => Some(err),
+            Error::SwapBuffers(ref err) => Some(err),
+            Error::Io(_, ref err) => Some(err),
+            Error::Window(..) | Error::Invalid(..) => None,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+use image;
+
+use grid::{Grid, Index, Vertex};
+
+/// Writes the values of `grid` as a 16-bit grayscale PNG, with 0 as black and 1 as
+/// white. Row `y` of the grid is row `y` of the image.
+pub fn write_heightmap_png(grid: &Grid, path: &Path) -> io::Result<()> {
+    let mut data = Vec::with_capacity(grid.as_ref().len() * 2);
+    for &value in grid.as_ref() {
+        let level = (value.max(0.0).min(1.0) * f64::from(u16::max_value())).round() as u16;
+        data.push((level >> 8) as u8);
+        data.push(level as u8);
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    image::png::PNGEncoder::new(file).encode(
+        &data,
+        grid.width(),
+        grid.height(),
+        image::ColorType::Gray(16),
+    )
+}
+
+/// Writes a triangle list as a Wavefront OBJ file, with positions and normals
+/// transformed by `model`.
+pub fn write_obj<W: Write>(
+    out: &mut W,
+    vertices: &[Vertex],
+    indices: &[Index],
+    model: Matrix4<f32>,
+) -> io::Result<()> {
+    let normal_model = Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate())
+        .invert()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the model matrix has a zero scale, so normals can't be transformed",
+            )
+        })?
+        .transpose();
+
+    for v in vertices {
+        let p = v.position();
+        let p = model * Vector4::new(p[0], p[1], p[2], 1.0);
+        writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for v in vertices {
+        let t = v.tex_coord();
+        writeln!(out, "vt {} {}", t[0], t[1])?;
+    }
+    for v in vertices {
+        let n = (normal_model * Vector3::from(v.normal())).normalize();
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    // OBJ indices start at 1, and each vertex uses the same index for its position,
+    // texture coordinate and normal.
+    for triangle in indices.chunks(3) {
+        let (a, b, c) = (triangle[0] + 1, triangle[1] + 1, triangle[2] + 1);
+        writeln!(out, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+    }
+    Ok(())
+}
+
+pub fn write_obj_file(
+    path: &Path,
+    vertices: &[Vertex],
+    indices: &[Index],
+    model: Matrix4<f32>,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_obj(&mut file, vertices, indices, model)?;
+    file.flush()
+}
@@ -1,14 +1,28 @@
 use glium;
-use cgmath::Matrix4;
-use visualizer::Visualizer;
+use glium::backend::Facade;
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+use error::{self, Error};
+use visualizer::{Index, Vertex, Visualizer};
 
 pub trait GeometryProvider<V, I>
 where
     V: glium::Vertex,
     I: glium::index::Index,
 {
-    fn get_geometry(&self) -> &Geometry<V, I>;
-    fn update(&mut self, _vis: &Visualizer) {}
+    /// All meshes to be drawn this frame, each with its own model matrix.
+    fn geometries(&self) -> Vec<&Geometry<V, I>>;
+    fn update(&mut self, _vis: &Visualizer) -> error::Result<()> {
+        Ok(())
+    }
+    /// Whether more calls to `update` are needed to show everything around the
+    /// camera, for providers that spread their work over several frames.
+    fn is_loading(&self) -> bool {
+        false
+    }
+    /// The noise values that grid values of 0 and 1 stand for, before normalization.
+    fn raw_range(&self) -> Option<(f64, f64)> {
+        None
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -25,6 +39,8 @@ where
     vertex_buffer: glium::VertexBuffer<V>,
     index_buffer: glium::IndexBuffer<I>,
     model: Matrix4<f32>,
+    bounds: Option<Bounds>,
+    texture_density: Option<f32>,
 }
 
 impl<V, I> Geometry<V, I>
@@ -41,9 +57,17 @@ where
             vertex_buffer,
             index_buffer,
             model,
+            bounds: None,
+            texture_density: None,
         }
     }
 
+    /// Sets the bounds of the vertices, before the model matrix is applied.
+    pub fn set_bounds(&mut self, bounds: Bounds) -> &mut Geometry<V, I> {
+        self.bounds = Some(bounds);
+        self
+    }
+
     pub fn vertex_buffer(&self) -> &glium::VertexBuffer<V> {
         &self.vertex_buffer
     }
@@ -53,6 +77,135 @@ where
     pub fn model(&self) -> &Matrix4<f32> {
         &self.model
     }
+    /// The bounds of the geometry in world space, if they are known.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds.map(|bounds| bounds.transform(&self.model))
+    }
+    /// Texture coordinate units per world unit, if the texture coordinates are a
+    /// planar projection of the vertex positions.
+    pub fn texture_density(&self) -> Option<f32> {
+        self.texture_density
+    }
+}
+
+impl Geometry<Vertex, Index> {
+    /// Uploads a triangle list and records its bounds and texture density.
+    ///
+    /// The texture coordinates are expected to be proportional to the x and y
+    /// coordinates of the vertices, as they are in grid meshes.
+    pub fn from_mesh<F: ?Sized + Facade>(
+        facade: &F,
+        vertices: &[Vertex],
+        indices: &[Index],
+        model: Matrix4<f32>,
+    ) -> error::Result<Geometry<Vertex, Index>> {
+        let vertex_buffer = glium::VertexBuffer::new(facade, vertices)
+            .map_err(|err| Error::resource("the terrain vertex buffer", err))?;
+        let index_buffer = glium::IndexBuffer::new(
+            facade,
+            glium::index::PrimitiveType::TrianglesList,
+            indices,
+        ).map_err(|err| Error::resource("the terrain index buffer", err))?;
+
+        let mut geometry = Geometry::new(vertex_buffer, index_buffer, model);
+        let positions = vertices.iter().map(|v| Vector3::from(v.position()));
+        if let Some(bounds) = Bounds::from_points(positions) {
+            geometry.set_bounds(bounds);
+        }
+
+        // Compare the two vertices furthest apart along x.
+        let compare_x = |a: &&Vertex, b: &&Vertex| {
+            a.position()[0].partial_cmp(&b.position()[0]).unwrap()
+        };
+        let first = vertices.iter().min_by(&compare_x);
+        let last = vertices.iter().max_by(&compare_x);
+        if let (Some(first), Some(last)) = (first, last) {
+            let dx = last.position()[0] - first.position()[0];
+            if dx > 0.0 {
+                let density = (last.tex_coord()[0] - first.tex_coord()[0]).abs() / dx;
+                geometry.texture_density = Some(density / model.x.truncate().magnitude());
+            }
+        }
+        Ok(geometry)
+    }
+}
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Bounds {
+    /// The smallest bounds containing all of `points`, or `None` if there are none.
+    pub fn from_points<I>(points: I) -> Option<Bounds>
+    where
+        I: IntoIterator<Item = Vector3<f32>>,
+    {
+        points.into_iter().fold(None, |bounds, p| match bounds {
+            Some(bounds) => Some(bounds.union(&Bounds { min: p, max: p })),
+            None => Some(Bounds { min: p, max: p }),
+        })
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Vector3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vector3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// The distances from `point` to the nearest and the furthest points of the
+    /// bounds; the nearest is 0 if `point` is inside.
+    pub fn distance_range(&self, point: Vector3<f32>) -> (f32, f32) {
+        let nearest = Vector3::new(
+            point.x.max(self.min.x).min(self.max.x),
+            point.y.max(self.min.y).min(self.max.y),
+            point.z.max(self.min.z).min(self.max.z),
+        );
+        let furthest = Vector3::new(
+            if point.x < self.center().x { self.max.x } else { self.min.x },
+            if point.y < self.center().y { self.max.y } else { self.min.y },
+            if point.z < self.center().z { self.max.z } else { self.min.z },
+        );
+        ((nearest - point).magnitude(), (furthest - point).magnitude())
+    }
+
+    /// The bounds of the corners of these bounds transformed by `matrix`.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Bounds {
+        let (a, b) = (self.min, self.max);
+        let corners = [
+            Vector3::new(a.x, a.y, a.z),
+            Vector3::new(b.x, a.y, a.z),
+            Vector3::new(a.x, b.y, a.z),
+            Vector3::new(b.x, b.y, a.z),
+            Vector3::new(a.x, a.y, b.z),
+            Vector3::new(b.x, a.y, b.z),
+            Vector3::new(a.x, b.y, b.z),
+            Vector3::new(b.x, b.y, b.z),
+        ];
+
+        Bounds::from_points(corners.iter().map(|c| {
+            let p = matrix * Vector4::new(c.x, c.y, c.z, 1.0);
+            Vector3::new(p.x, p.y, p.z)
+        })).unwrap()
+    }
 }
 
 impl<V, I> GeometryProvider<V, I> for Geometry<V, I>
@@ -60,8 +213,8 @@ where
     V: glium::Vertex,
     I: glium::index::Index,
 {
-    fn get_geometry(&self) -> &Geometry<V, I> {
-        &*self
+    fn geometries(&self) -> Vec<&Geometry<V, I>> {
+        vec![&*self]
     }
 }
 
@@ -0,0 +1,47 @@
+#version 330
+
+uniform sampler1D color_ramp;
+
+// Which property of the surface is shown, see `ShaderMode::debug_view`.
+uniform int debug_view;
+// Distances from the camera shown as black and white in the depth view.
+uniform vec2 depth_range;
+
+const int VIEW_HEIGHT = 1;
+const int VIEW_NORMALS = 2;
+const int VIEW_TEX_COORDS = 3;
+const int VIEW_SLOPE = 4;
+const int VIEW_DEPTH = 5;
+
+in Data {
+    vec3 position;
+    vec3 normal;
+    vec3 eye;
+    vec3 light_dir;
+    vec2 tex_coord;
+    vec3 world_position;
+    float depth;
+} DataIn;
+
+out vec4 color;
+
+void main() {
+    vec3 normal = normalize(DataIn.normal);
+
+    if(debug_view == VIEW_HEIGHT) {
+        color = texture(color_ramp, 1.0 - DataIn.position.z);
+    } else if(debug_view == VIEW_NORMALS) {
+        color = vec4(normal * 0.5 + 0.5, 1.0);
+    } else if(debug_view == VIEW_TEX_COORDS) {
+        color = vec4(fract(DataIn.tex_coord), 0.0, 1.0);
+    } else if(debug_view == VIEW_SLOPE) {
+        float slope = degrees(acos(clamp(abs(normal.z), 0.0, 1.0)));
+        color = texture(color_ramp, slope / 90.0);
+    } else if(debug_view == VIEW_DEPTH) {
+        float range = max(depth_range.y - depth_range.x, 0.0001);
+        float depth = clamp((DataIn.depth - depth_range.x) / range, 0.0, 1.0);
+        color = vec4(vec3(depth), 1.0);
+    } else {
+        color = vec4(1.0, 0.0, 1.0, 1.0);
+    }
+}
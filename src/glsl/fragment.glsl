@@ -1,7 +1,9 @@
 #version 330
 
+uniform sampler1D color_ramp;
+
 in vec3 frag_position;
-in vec4 frag_color;
+in float frag_height;
 out vec4 color;
 
 float apply_srgb_component(float component) {
@@ -27,6 +29,5 @@ vec4 apply_srgb(vec4 color) {
 }
 
 void main() {
-    //color = apply_srgb(frag_color);
-    color = frag_color;
+    color = texture(color_ramp, frag_height);
 }
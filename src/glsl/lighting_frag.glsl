@@ -1,8 +1,38 @@
 #version 330
 
+uniform sampler2DArray layer_textures;
+uniform int num_layers;
+
+uniform sampler1D color_ramp;
+uniform bool use_color_ramp;
+
+struct Material {
+    vec4 ambient;
+    vec4 diffuse;
+    vec4 specular;
+    float shine;
+};
+
+uniform Materials {
+    Material mat[5];
+};
+
+struct Layer {
+    vec2 height;
+    vec2 slope;
+    float scale;
+    float blend;
+};
+
+uniform Layers {
+    Layer layer[16];
+};
+
 in Data {
-    vec4 color;
-    vec3 normal;
+    vec4 diffuse;
+    vec4 specular;
+    vec2 tex_coord;
+    float height;
 } DataIn;
 
 out vec4 out_color;
@@ -22,13 +52,42 @@ float apply_srgb_component(float component) {
 
 vec4 apply_srgb(vec4 color) {
     return vec4(
-        apply_srgb_component(color.r), 
-        apply_srgb_component(color.g), 
-        apply_srgb_component(color.b), 
+        apply_srgb_component(color.r),
+        apply_srgb_component(color.g),
+        apply_srgb_component(color.b),
         color.a
     );
 }
 
+float in_range(vec2 range, float blend, float value) {
+    blend = max(blend, 0.0001);
+    return smoothstep(range.x - blend, range.x, value)
+        * (1.0 - smoothstep(range.y, range.y + blend, value));
+}
+
+// Paints the layers over each other by height only, since slopes need per-pixel
+// normals. As with slope layers turned off, the layers limited to a range of slopes
+// are left out.
+vec4 texture_layers(float height) {
+    vec4 color = vec4(0.0);
+    for(int i = 0; i < num_layers; i++) {
+        vec4 layer_color =
+            texture(layer_textures, vec3(DataIn.tex_coord * layer[i].scale, i));
+
+        bool has_slope_range = layer[i].slope.x > 0.0 || layer[i].slope.y < 90.0;
+        float weight = has_slope_range
+            ? 0.0
+            : in_range(layer[i].height, layer[i].blend, height);
+        color = i == 0 ? layer_color : mix(color, layer_color, weight);
+    }
+    return color;
+}
+
 void main() {
-    out_color = apply_srgb(DataIn.color);
+    vec4 mat_color = use_color_ramp
+        ? texture(color_ramp, DataIn.height)
+        : texture_layers(DataIn.height);
+    vec4 lit_color = mat_color * DataIn.diffuse + DataIn.specular;
+
+    out_color = apply_srgb(max(lit_color, mat[0].ambient * mat_color));
 }
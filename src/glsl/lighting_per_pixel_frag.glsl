@@ -2,10 +2,15 @@
 
 uniform mat4 view;
 
-uniform sampler2D grass_texture;
-uniform sampler2D dirt_texture;
-uniform sampler2D snow_texture;
-uniform sampler2D water_texture;
+uniform sampler2DArray layer_textures;
+uniform int num_layers;
+// Texture coordinate units per world unit.
+uniform float texture_density;
+uniform bool use_triplanar;
+uniform bool use_slope_layers;
+
+uniform sampler1D color_ramp;
+uniform bool use_color_ramp;
 
 layout(std140) uniform Lights {
     vec4 light_color;
@@ -23,12 +28,28 @@ uniform Materials {
     Material mat[5];
 };
 
+struct Layer {
+    vec2 height;
+    vec2 slope;
+    float scale;
+    float blend;
+};
+
+uniform Layers {
+    Layer layer[16];
+};
+
+// Degrees over which layers fade in and out at the ends of their slope range.
+const float SLOPE_BLEND = 5.0;
+
 in Data {
     vec3 position;
     vec3 normal;
     vec3 eye;
     vec3 light_dir;
     vec2 tex_coord;
+    vec3 world_position;
+    float depth;
 } DataIn;
 
 out vec4 color;
@@ -55,41 +76,46 @@ vec4 apply_srgb(vec4 color) {
     );
 }
 
-vec4 gradient(float value) {
-    vec4 water = vec4(0.1, 0.1, 0.6, 1.0);
-    vec4 grass = vec4(0.1, 0.6, 0.1, 1.0);
-    vec4 dirt = vec4(0.910, 0.435, 0.220, 1.0);
-    vec4 stone = vec4(0.5, 0.5, 0.5, 1.0);
-
-    if(value < 0.30) {
-        float level = smoothstep(0.15, 0.30, value);
-        return water * (1.0 - level) + grass * level;
-    } else if(value < 0.60) {
-        float level = smoothstep(0.45, 0.55, value);
-        return grass * (1.0 - level) + dirt * level;
-    } else {
-        float level = smoothstep(0.70, 0.90, value);
-        return dirt * (1.0 - level) + stone * level; 
-    }
+float in_range(vec2 range, float blend, float value) {
+    blend = max(blend, 0.0001);
+    return smoothstep(range.x - blend, range.x, value)
+        * (1.0 - smoothstep(range.y, range.y + blend, value));
+}
+
+// Blends projections of the texture along each axis, weighted by how much the
+// surface faces that axis, so steep faces aren't stretched.
+vec4 triplanar(int i, vec3 normal) {
+    vec3 weights = pow(abs(normal), vec3(4.0));
+    weights /= weights.x + weights.y + weights.z;
+
+    vec3 p = DataIn.world_position * texture_density * layer[i].scale;
+    return texture(layer_textures, vec3(p.yz, i)) * weights.x
+        + texture(layer_textures, vec3(p.xz, i)) * weights.y
+        + texture(layer_textures, vec3(p.xy, i)) * weights.z;
 }
 
-vec4 texture_gradient(float value) {
-    vec4 tex1_color = texture(grass_texture, DataIn.tex_coord * 4.0);
-    vec4 tex2_color = texture(dirt_texture, DataIn.tex_coord * 4.0);
-    vec4 tex3_color = texture(snow_texture, DataIn.tex_coord * 4.0);
-    vec4 tex4_color = texture(water_texture, DataIn.tex_coord * 4.0);
-
-    if(value < 0.30) {
-        float level = smoothstep(0.20, 0.30, value);
-        return tex4_color * (1.0 - level) + tex1_color * level;
-    } else if(value < 0.70) {
-        float level = smoothstep(0.50, 0.65, value);
-        return tex1_color * (1.0 - level) + tex2_color * level;
-    } else if(value >= 0.70) {
-        float level = smoothstep(0.70, 0.85, value);
-        return tex2_color * (1.0 - level) + tex3_color * level;
+// Paints the layers over each other, each one where the height and slope are in its
+// ranges. Without slope layers, the ones limited to a range of slopes are left out.
+vec4 texture_layers(float height, float slope, vec3 normal) {
+    vec4 color = vec4(0.0);
+    for(int i = 0; i < num_layers; i++) {
+        vec4 layer_color = use_triplanar
+            ? triplanar(i, normal)
+            : texture(layer_textures, vec3(DataIn.tex_coord * layer[i].scale, i));
+
+        float slope_weight;
+        if(use_slope_layers) {
+            slope_weight = in_range(layer[i].slope, SLOPE_BLEND, slope);
+        } else {
+            bool has_slope_range = layer[i].slope.x > 0.0 || layer[i].slope.y < 90.0;
+            slope_weight = has_slope_range ? 0.0 : 1.0;
+        }
+        float weight = in_range(layer[i].height, layer[i].blend, height) * slope_weight;
+        color = i == 0 ? layer_color : mix(color, layer_color, weight);
     }
+    return color;
 }
+
 int texture_mat(float value) {
     if(value < 0.25) {
         return 1;
@@ -100,7 +126,12 @@ int texture_mat(float value) {
 void main() {
     Material frag_mat = mat[texture_mat(1.0 - DataIn.position.z)];
     float intensity = max(dot(DataIn.normal, DataIn.light_dir), 0.0);
-    vec4 mat_color = texture_gradient(1.0 - DataIn.position.z);
+    float height = 1.0 - DataIn.position.z;
+    vec3 normal = normalize(DataIn.normal);
+    float slope = degrees(acos(clamp(abs(normal.z), 0.0, 1.0)));
+    vec4 mat_color = use_color_ramp
+        ? texture(color_ramp, height)
+        : texture_layers(height, slope, normal);
     vec4 light_color = light_color * intensity;
     vec4 diffuse_color = mat_color * frag_mat.diffuse * light_color;
     vec4 specular_color = vec4(0.0);
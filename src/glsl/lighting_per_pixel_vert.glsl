@@ -20,6 +20,9 @@ out Data {
     vec3 eye;
     vec3 light_dir;
     vec2 tex_coord;
+    vec3 world_position;
+    // Distance from the camera.
+    float depth;
 } DataOut;
 
 void main() {
@@ -34,4 +37,6 @@ void main() {
     DataOut.eye = normalize(-(mv * vec4(position, 1.0)).xyz);
     DataOut.light_dir = normalize(position - light_pos);
     DataOut.tex_coord = tex_coord;
+    DataOut.world_position = (model * vec4(position, 1.0)).xyz;
+    DataOut.depth = length((mv * vec4(position, 1.0)).xyz);
 }
@@ -7,29 +7,38 @@ uniform mat3 normal_model;
 
 in vec3 position;
 in vec3 normal;
+in vec2 tex_coord;
 
-uniform Lights {
-    vec3 dir;
+layout(std140) uniform Lights {
+    vec4 light_color;
+    vec3 light_pos;
 };
 
-uniform Materials {
+struct Material {
     vec4 ambient;
     vec4 diffuse;
     vec4 specular;
     float shine;
 };
 
+uniform Materials {
+    Material mat[5];
+};
+
+// The lighting is computed here and interpolated over each triangle; only the
+// texture is sampled per pixel.
 out Data {
-    vec4 color;
-    vec3 normal;
+    vec4 diffuse;
+    vec4 specular;
+    vec2 tex_coord;
+    float height;
 } DataOut;
 
-vec4 gradient(float value) {
-    if(value < 0.5) {
-        return vec4(0.0, position.z * 2.0, 1.0 - position.z * 2.0, 1.0);
-    } else {
-        return vec4((position.z - 0.5) * 2.0, 1.0 - (position.z - 0.5) * 2.0, 0.0, 1.0);
+int texture_mat(float value) {
+    if(value < 0.25) {
+        return 1;
     }
+    return 0;
 }
 
 void main() {
@@ -37,29 +46,24 @@ void main() {
     mat4 mvp = perspective * mv;
     gl_Position = mvp * vec4(position, 1.0);
 
-    //vec4 mat_color = vec4(1.0);
-    vec4 mat_color = gradient(position.z);
-    vec3 model_normal = normalize(normal_model * normal);
-    float intensity = max(dot(model_normal, dir), 0.0);
+    float height = 1.0 - position.z;
+    Material vert_mat = mat[texture_mat(height)];
+    vec3 world_normal = normalize(normal_model * normal);
+    vec3 light_dir = normalize(position - light_pos);
+    float intensity = max(dot(world_normal, light_dir), 0.0);
     vec4 specular_color = vec4(0.0);
 
     if(intensity > 0.0) {
-        vec3 local_pos = vec3(mv * vec4(position, 1.0));
-        vec3 local_norm = vec3(vec4(model_normal, 0.0));
-        vec3 local_light_dir = vec3(view * vec4(dir, 0.0));
-
-        vec3 eye = normalize(-local_pos);
+        vec3 eye = normalize(-(mv * vec4(position, 1.0)).xyz);
+        vec3 local_light_dir = vec3(view * vec4(light_dir, 0.0));
         vec3 h = normalize(local_light_dir + eye);
 
-        float specular_intensity = max(dot(h,local_norm), 0.0);
-        specular_color = specular * pow(specular_intensity, shine);
+        float specular_intensity = max(dot(h, world_normal), 0.0);
+        specular_color = vert_mat.specular * pow(specular_intensity, vert_mat.shine);
     }
 
-    vec4 diffuse_color = mat_color * vec4(intensity);
-    vec4 ambient_color = mat_color * ambient;
-
-    vec4 linear_color = max(ambient_color, min(diffuse_color + specular_color, 1.0));
-
-    DataOut.color = linear_color;
-    DataOut.normal = normal;
+    DataOut.diffuse = vert_mat.diffuse * light_color * intensity;
+    DataOut.specular = specular_color;
+    DataOut.tex_coord = tex_coord;
+    DataOut.height = height;
 }
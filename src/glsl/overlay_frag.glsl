@@ -0,0 +1,10 @@
+#version 330
+
+uniform sampler2D overlay;
+
+in vec2 frag_tex_coord;
+out vec4 color;
+
+void main() {
+    color = texture(overlay, frag_tex_coord);
+}
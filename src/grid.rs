@@ -1,4 +1,6 @@
 use std::f64;
+use std::fmt;
+use std::str::FromStr;
 
 use cgmath::{self, InnerSpace, Vector3};
 use noise_lib;
@@ -11,11 +13,32 @@ pub struct Vertex {
 }
 implement_vertex!(Vertex, position, normal, tex_coord);
 
+impl Vertex {
+    pub fn new(position: [f32; 3], normal: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+        Vertex {
+            position,
+            normal,
+            tex_coord,
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+    pub fn normal(&self) -> [f32; 3] {
+        self.normal
+    }
+    pub fn tex_coord(&self) -> [f32; 2] {
+        self.tex_coord
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Grid {
     vals: Vec<f64>,
     width: u32,
     height: u32,
+    raw_range: (f64, f64),
 }
 
 pub type Index = u32;
@@ -26,20 +49,54 @@ impl Grid {
         Grid {
             width: width,
             height: height,
-            vals: vec![0.0; (width * height) as usize],
+            vals: vec![0.0; width as usize * height as usize],
+            raw_range: (0.0, 1.0),
         }
     }
 
     pub fn from_vec(vec: Vec<f64>, width: u32, height: u32) -> Grid {
         assert!(width > 1 && height > 1);
-        assert!((width * height) as usize == vec.len());
+        assert!(width as usize * height as usize == vec.len());
         Grid {
             width: width,
             height: height,
             vals: vec,
+            raw_range: (0.0, 1.0),
+        }
+    }
+
+    /// Maps the values of the grid into `[0, 1]`, keeping the values that were mapped
+    /// to 0 and 1 in `raw_range`.
+    pub fn normalize(&mut self, normalization: Normalization) {
+        let (raw_min, raw_max) = self.raw_range;
+        let to_raw = |value: f64| raw_min + value * (raw_max - raw_min);
+
+        match normalization {
+            Normalization::MinMax => {
+                let (mut min, mut max) = (f64::MAX, f64::MIN);
+                for &value in &self.vals {
+                    min = min.min(value);
+                    max = max.max(value);
+                }
+
+                let coeff = if max > min { 1.0 / (max - min) } else { 0.0 };
+                for value in &mut self.vals {
+                    *value = (*value - min) * coeff;
+                }
+                self.raw_range = (to_raw(min), to_raw(max));
+            }
+            Normalization::Clamp => for value in &mut self.vals {
+                *value = value.max(0.0).min(1.0);
+            },
         }
     }
 
+    /// The values before normalization that 0 and 1 stand for, which are the grid's
+    /// own values unless it was normalized with `Normalization::MinMax`.
+    pub fn raw_range(&self) -> (f64, f64) {
+        self.raw_range
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -47,15 +104,103 @@ impl Grid {
         self.height
     }
 
+    pub fn value(&self, x: u32, y: u32) -> f64 {
+        self.vals[(x + y * self.width) as usize]
+    }
+
+    /// Estimates the surface normal at a grid point using central differences,
+    /// falling back to one-sided differences along the border.
+    ///
+    /// Unlike the normals from `gen_vertex_buffer`, this only depends on the grid
+    /// values, so meshes built from different regions agree along shared edges.
+    pub fn normal_at(&self, x: u32, y: u32) -> Vector3<f32> {
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (y0, y1) = (y.saturating_sub(1), (y + 1).min(self.height - 1));
+
+        let dzdx = (self.value(x1, y) - self.value(x0, y)) / f64::from(x1 - x0);
+        let dzdy = (self.value(x, y1) - self.value(x, y0)) / f64::from(y1 - y0);
+
+        normalize_or_up(Vector3::new(-dzdx as f32, -dzdy as f32, 1.0))
+    }
+
+    /// Generates a mesh for the `cells` grid cells starting at `origin`, keeping only
+    /// every `step`th row and column (the last row and column are always kept).
+    ///
+    /// Positions and texture coordinates are in the same space as `gen_vertex_buffer`,
+    /// so regions of one grid can share a model matrix. If `skirt_depth` is non-zero,
+    /// a skirt pushed `skirt_depth` along +z is hung from each edge of the region to
+    /// hide cracks against neighbours meshed with a different step.
+    pub fn gen_region_vertex_buffer(
+        &self,
+        origin: (u32, u32),
+        cells: (u32, u32),
+        step: u32,
+        skirt_depth: f32,
+    ) -> (Vec<Vertex>, Vec<Index>) {
+        assert!(step > 0 && cells.0 > 0 && cells.1 > 0);
+        assert!(origin.0 + cells.0 < self.width && origin.1 + cells.1 < self.height);
+
+        let xs = region_samples(origin.0, cells.0, step);
+        let ys = region_samples(origin.1, cells.1, step);
+        let (cols, rows) = (xs.len() as Index, ys.len() as Index);
+
+        let mut vertex_buffer = Vec::with_capacity(xs.len() * ys.len());
+        let mut index_buffer: Vec<Index> =
+            Vec::with_capacity((xs.len() - 1) * (ys.len() - 1) * 6);
+
+        for &y in &ys {
+            for &x in &xs {
+                vertex_buffer.push(self.vertex_at(x, y));
+            }
+        }
+
+        for row in 0..(rows - 1) {
+            for col in 0..(cols - 1) {
+                let start_index = col + row * cols;
+                index_buffer.push(start_index);
+                index_buffer.push(start_index + cols);
+                index_buffer.push(start_index + 1);
+
+                index_buffer.push(start_index + cols);
+                index_buffer.push(start_index + cols + 1);
+                index_buffer.push(start_index + 1);
+            }
+        }
+
+        if skirt_depth != 0.0 {
+            let top: Vec<Index> = (0..cols).collect();
+            let bottom: Vec<Index> = (0..cols).map(|col| col + (rows - 1) * cols).collect();
+            let left: Vec<Index> = (0..rows).map(|row| row * cols).collect();
+            let right: Vec<Index> = (0..rows).map(|row| row * cols + cols - 1).collect();
+
+            for edge in &[top, bottom, left, right] {
+                add_skirt(&mut vertex_buffer, &mut index_buffer, edge, skirt_depth);
+            }
+        }
+
+        (vertex_buffer, index_buffer)
+    }
+
+    /// The vertex for a single grid point, with its normal from `normal_at`.
+    pub fn vertex_at(&self, x: u32, y: u32) -> Vertex {
+        Vertex {
+            position: [x as f32, y as f32, self.value(x, y) as f32],
+            normal: self.normal_at(x, y).into(),
+            tex_coord: [
+                (x as f32) / (self.width - 1) as f32,
+                (y as f32) / (self.height - 1) as f32,
+            ],
+        }
+    }
+
     pub fn gen_vertex_buffer(&self) -> (Vec<Vertex>, Vec<Index>) {
+        self.gen_vertex_buffer_with(&MeshOptions::default())
+    }
+
+    pub fn gen_vertex_buffer_with(&self, options: &MeshOptions) -> (Vec<Vertex>, Vec<Index>) {
         let (width, height) = (self.width, self.height);
         let num_vals = (width as usize) * (height as usize);
         let mut vertex_buffer = Vec::with_capacity(num_vals);
-        let mut index_buffer: Vec<Index> =
-            Vec::with_capacity((width as usize - 1) * (height as usize - 1) * 6);
-
-        let mut normal_counts = vec![0; num_vals];
-        let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); num_vals];
 
         for y in 0..height {
             for x in 0..width {
@@ -71,57 +216,401 @@ impl Grid {
             }
         }
 
-        for y in 0..(height - 1) {
+        let index_buffer = self.gen_index_buffer(options.triangulation);
+        let normals = self.compute_normals(options.normals, &index_buffer);
+
+        for (vertex, normal) in vertex_buffer.iter_mut().zip(normals) {
+            vertex.normal = normal.into();
+        }
+
+        (vertex_buffer, index_buffer)
+    }
+
+    /// Like `gen_vertex_buffer_with`, but with separate vertices for every triangle
+    /// so each face is lit with its own normal.
+    pub fn gen_flat_vertex_buffer(&self, options: &MeshOptions) -> (Vec<Vertex>, Vec<Index>) {
+        let (vertices, indices) = self.gen_vertex_buffer_with(options);
+        flatten_mesh(&vertices, &indices)
+    }
+
+    /// Generates two triangles per cell, indexing the grid points in row-major order.
+    pub fn gen_index_buffer(&self, triangulation: Triangulation) -> Vec<Index> {
+        let width = self.width;
+        let mut index_buffer: Vec<Index> =
+            Vec::with_capacity((width as usize - 1) * (self.height as usize - 1) * 6);
+
+        for y in 0..(self.height - 1) {
             for x in 0..(width - 1) {
                 let start_index = x + y * width;
-                index_buffer.push(start_index);
-                index_buffer.push(start_index + width);
-                index_buffer.push(start_index + 1);
 
-                let a1 = Vector3::from(vertex_buffer[start_index as usize].position);
-                let a2 = Vector3::from(vertex_buffer[(start_index + width) as usize].position);
-                let a3 = Vector3::from(vertex_buffer[(start_index + 1) as usize].position);
-                let a4 = Vector3::from(vertex_buffer[(start_index + width + 1) as usize].position);
+                if self.uses_main_diagonal(triangulation, x, y) {
+                    index_buffer.push(start_index);
+                    index_buffer.push(start_index + width);
+                    index_buffer.push(start_index + width + 1);
 
-                let a1a2 = a2 - a1;
-                let a1a3 = a3 - a1;
+                    index_buffer.push(start_index);
+                    index_buffer.push(start_index + width + 1);
+                    index_buffer.push(start_index + 1);
+                } else {
+                    index_buffer.push(start_index);
+                    index_buffer.push(start_index + width);
+                    index_buffer.push(start_index + 1);
 
-                let a2a4 = a4 - a2;
-                let a2a3 = a3 - a2;
-                let normal1 = a1a3.cross(a1a2).normalize();
-                let normal2 = a2a3.cross(a2a4).normalize();
+                    index_buffer.push(start_index + width);
+                    index_buffer.push(start_index + width + 1);
+                    index_buffer.push(start_index + 1);
+                }
+            }
+        }
 
-                let i1 = start_index as usize;
-                let i3 = (start_index + 1) as usize;
-                let i2 = (start_index + width) as usize;
-                let i4 = (start_index + width + 1) as usize;
+        index_buffer
+    }
 
-                normals[i1] += normal1;
-                normals[i2] += normal1 + normal2;
-                normals[i3] += normal1 + normal2;
-                normals[i4] += normal2;
+    /// Whether the cell at `(x, y)` is split from `(x, y)` to `(x + 1, y + 1)` rather
+    /// than from `(x, y + 1)` to `(x + 1, y)`.
+    fn uses_main_diagonal(&self, triangulation: Triangulation, x: u32, y: u32) -> bool {
+        match triangulation {
+            Triangulation::Uniform => false,
+            Triangulation::Alternating => (x + y) % 2 == 1,
+            Triangulation::Zigzag => y % 2 == 1,
+            Triangulation::SlopeAware => {
+                let main = (self.value(x, y) - self.value(x + 1, y + 1)).abs();
+                let anti = (self.value(x, y + 1) - self.value(x + 1, y)).abs();
+                main < anti
+            }
+        }
+    }
 
-                normal_counts[i1] += 1;
-                normal_counts[i2] += 2;
-                normal_counts[i3] += 2;
-                normal_counts[i4] += 1;
+    /// Computes one normal per grid point, in row-major order.
+    ///
+    /// `indices` is the triangulation of the grid points, which is only used by the
+    /// methods based on face normals.
+    pub fn compute_normals(&self, method: NormalMethod, indices: &[Index]) -> Vec<Vector3<f32>> {
+        match method {
+            NormalMethod::FaceAverage | NormalMethod::AreaWeighted => {
+                let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); self.vals.len()];
 
-                index_buffer.push(start_index + width);
-                index_buffer.push(start_index + width + 1);
-                index_buffer.push(start_index + 1);
+                for triangle in indices.chunks(3) {
+                    let a1 = self.point(triangle[0]);
+                    let a2 = self.point(triangle[1]);
+                    let a3 = self.point(triangle[2]);
+
+                    // The cross product's length is twice the triangle's area.
+                    let mut normal = (a3 - a1).cross(a2 - a1);
+                    if method == NormalMethod::FaceAverage {
+                        normal = normalize_or_up(normal);
+                    }
+
+                    for &i in triangle {
+                        normals[i as usize] += normal;
+                    }
+                }
+
+                normals.into_iter().map(normalize_or_up).collect()
+            }
+            NormalMethod::CentralDifference => {
+                let mut normals = Vec::with_capacity(self.vals.len());
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        normals.push(self.normal_at(x, y));
+                    }
+                }
+                normals
+            }
+            NormalMethod::Sobel => {
+                let mut normals = Vec::with_capacity(self.vals.len());
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        normals.push(self.sobel_normal_at(x, y));
+                    }
+                }
+                normals
             }
         }
+    }
 
-        for y in 0..height {
-            for x in 0..width {
-                let index = (x + y * width) as usize;
-                vertex_buffer[index].normal = (normals[index] / normal_counts[index] as f32)
-                    .normalize()
-                    .into();
-            }
+    /// Estimates the surface normal at a grid point with a 3x3 Sobel filter,
+    /// repeating the border values past the edges of the grid.
+    pub fn sobel_normal_at(&self, x: u32, y: u32) -> Vector3<f32> {
+        let (x, y) = (i64::from(x), i64::from(y));
+        let z = |dx: i64, dy: i64| self.clamped_value(x + dx, y + dy);
+
+        let dzdx = ((z(1, -1) + 2.0 * z(1, 0) + z(1, 1)) - (z(-1, -1) + 2.0 * z(-1, 0) + z(-1, 1)))
+            / 8.0;
+        let dzdy = ((z(-1, 1) + 2.0 * z(0, 1) + z(1, 1)) - (z(-1, -1) + 2.0 * z(0, -1) + z(1, -1)))
+            / 8.0;
+
+        normalize_or_up(Vector3::new(-dzdx as f32, -dzdy as f32, 1.0))
+    }
+
+    fn clamped_value(&self, x: i64, y: i64) -> f64 {
+        let x = x.max(0).min(i64::from(self.width) - 1) as u32;
+        let y = y.max(0).min(i64::from(self.height) - 1) as u32;
+        self.value(x, y)
+    }
+
+    fn point(&self, index: Index) -> Vector3<f32> {
+        let (x, y) = (index % self.width, index / self.width);
+        Vector3::new(x as f32, y as f32, self.vals[index as usize] as f32)
+    }
+}
+
+/// How each grid cell is split into two triangles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Triangulation {
+    /// Every cell is split along the same diagonal.
+    Uniform,
+    /// The diagonal flips from cell to cell in a checkerboard pattern.
+    Alternating,
+    /// The diagonal flips from row to row.
+    Zigzag,
+    /// Each cell is split along the diagonal whose end points are closest in height,
+    /// which follows ridges and valleys more closely.
+    SlopeAware,
+}
+
+impl Triangulation {
+    pub const NAMES: &'static [&'static str] =
+        &["uniform", "alternating", "zigzag", "slope-aware"];
+}
+
+impl Default for Triangulation {
+    fn default() -> Triangulation {
+        Triangulation::Uniform
+    }
+}
+
+impl FromStr for Triangulation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Triangulation, String> {
+        match s {
+            "uniform" => Ok(Triangulation::Uniform),
+            "alternating" => Ok(Triangulation::Alternating),
+            "zigzag" => Ok(Triangulation::Zigzag),
+            "slope-aware" => Ok(Triangulation::SlopeAware),
+            _ => Err(format!("unknown triangulation '{}'", s)),
         }
+    }
+}
 
-        (vertex_buffer, index_buffer)
+impl fmt::Display for Triangulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Triangulation::Uniform => "uniform",
+            Triangulation::Alternating => "alternating",
+            Triangulation::Zigzag => "zigzag",
+            Triangulation::SlopeAware => "slope-aware",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How vertex normals are computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalMethod {
+    /// Average of the unit normals of the triangles around each vertex.
+    FaceAverage,
+    /// Average of the triangle normals around each vertex, weighted by area.
+    AreaWeighted,
+    /// Central differences of the neighbouring grid values.
+    CentralDifference,
+    /// A 3x3 Sobel filter over the grid values, which smooths out noise.
+    Sobel,
+}
+
+impl NormalMethod {
+    pub const NAMES: &'static [&'static str] =
+        &["face-average", "area-weighted", "central-difference", "sobel"];
+}
+
+impl Default for NormalMethod {
+    fn default() -> NormalMethod {
+        NormalMethod::FaceAverage
+    }
+}
+
+impl FromStr for NormalMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NormalMethod, String> {
+        match s {
+            "face-average" => Ok(NormalMethod::FaceAverage),
+            "area-weighted" => Ok(NormalMethod::AreaWeighted),
+            "central-difference" => Ok(NormalMethod::CentralDifference),
+            "sobel" => Ok(NormalMethod::Sobel),
+            _ => Err(format!("unknown normal method '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for NormalMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            NormalMethod::FaceAverage => "face-average",
+            NormalMethod::AreaWeighted => "area-weighted",
+            NormalMethod::CentralDifference => "central-difference",
+            NormalMethod::Sobel => "sobel",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Whether normals are interpolated across faces or constant per face.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shading {
+    Smooth,
+    Flat,
+}
+
+impl Shading {
+    pub const NAMES: &'static [&'static str] = &["smooth", "flat"];
+}
+
+impl Default for Shading {
+    fn default() -> Shading {
+        Shading::Smooth
+    }
+}
+
+impl FromStr for Shading {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shading, String> {
+        match s {
+            "smooth" => Ok(Shading::Smooth),
+            "flat" => Ok(Shading::Flat),
+            _ => Err(format!("unknown shading '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Shading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Shading::Smooth => "smooth",
+            Shading::Flat => "flat",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How noise values are mapped to heights in `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Normalization {
+    /// The lowest and highest values of the grid are stretched to 0 and 1.
+    MinMax,
+    /// Values are only clamped, so heights can be compared between grids and seeds.
+    Clamp,
+}
+
+impl Normalization {
+    pub const NAMES: &'static [&'static str] = &["minmax", "clamp"];
+}
+
+impl Default for Normalization {
+    fn default() -> Normalization {
+        Normalization::MinMax
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Normalization, String> {
+        match s {
+            "minmax" => Ok(Normalization::MinMax),
+            "clamp" => Ok(Normalization::Clamp),
+            _ => Err(format!("unknown normalization '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Normalization::MinMax => "minmax",
+            Normalization::Clamp => "clamp",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshOptions {
+    pub triangulation: Triangulation,
+    pub normals: NormalMethod,
+}
+
+/// Converts an indexed triangle mesh into a faceted one, giving each triangle its
+/// own three vertices carrying the face normal.
+///
+/// Works on any heightfield mesh, such as the output of `simplify::simplify_grid`.
+/// Face normals are oriented towards +z like the rest of the grid normals.
+pub fn flatten_mesh(vertices: &[Vertex], indices: &[Index]) -> (Vec<Vertex>, Vec<Index>) {
+    let mut vertex_buffer = Vec::with_capacity(indices.len());
+
+    for triangle in indices.chunks(3) {
+        let a1 = Vector3::from(vertices[triangle[0] as usize].position);
+        let a2 = Vector3::from(vertices[triangle[1] as usize].position);
+        let a3 = Vector3::from(vertices[triangle[2] as usize].position);
+
+        let mut normal = normalize_or_up((a3 - a1).cross(a2 - a1));
+        if normal.z < 0.0 {
+            normal = -normal;
+        }
+
+        for &i in triangle {
+            let mut vertex = vertices[i as usize];
+            vertex.normal = normal.into();
+            vertex_buffer.push(vertex);
+        }
+    }
+
+    let index_buffer = (0..vertex_buffer.len() as Index).collect();
+    (vertex_buffer, index_buffer)
+}
+
+/// Normalizes `v`, returning +z for vectors too short to have a direction, such as
+/// the sum of the normals around a degenerate triangle.
+fn normalize_or_up(v: Vector3<f32>) -> Vector3<f32> {
+    let length = v.magnitude();
+    if length > 1e-12 && length.is_finite() {
+        v / length
+    } else {
+        Vector3::new(0.0, 0.0, 1.0)
+    }
+}
+
+fn region_samples(start: u32, cells: u32, step: u32) -> Vec<u32> {
+    let mut samples: Vec<u32> = (0..cells).filter(|i| i % step == 0).map(|i| start + i).collect();
+    samples.push(start + cells);
+    samples
+}
+
+fn add_skirt(
+    vertex_buffer: &mut Vec<Vertex>,
+    index_buffer: &mut Vec<Index>,
+    edge: &[Index],
+    depth: f32,
+) {
+    let first_skirt_index = vertex_buffer.len() as Index;
+
+    for &i in edge {
+        let mut vertex = vertex_buffer[i as usize];
+        vertex.position[2] += depth;
+        vertex_buffer.push(vertex);
+    }
+
+    for (i, pair) in edge.windows(2).enumerate() {
+        let skirt_index = first_skirt_index + i as Index;
+        index_buffer.push(pair[0]);
+        index_buffer.push(skirt_index);
+        index_buffer.push(pair[1]);
+
+        index_buffer.push(skirt_index);
+        index_buffer.push(skirt_index + 1);
+        index_buffer.push(pair[1]);
     }
 }
 
@@ -136,7 +625,11 @@ impl AsMut<[f64]> for Grid {
     }
 }
 
-pub fn make_noise_grid<N>(perlin: &N, dimensions: (u32, u32)) -> Grid
+pub fn make_noise_grid<N>(
+    perlin: &N,
+    dimensions: (u32, u32),
+    normalization: Normalization,
+) -> Grid
 where
     N: noise_lib::noise::Noise<IndexType = cgmath::Vector2<f64>>,
 {
@@ -145,32 +638,194 @@ where
     let dx = 1.0 / f64::from(width);
     let dy = 1.0 / f64::from(height);
 
-    let (mut min, mut max) = (f64::MAX, f64::MIN);
-
     let mut grid_vec = Vec::with_capacity(width as usize * height as usize);
 
     for y in 0..height {
         let perlin_y = f64::from(y) * dy;
         for x in 0..width {
             let perlin_x = f64::from(x) * dx;
+            grid_vec.push(0.5 + perlin.value_at(cgmath::Vector2::new(perlin_x, perlin_y)));
+        }
+    }
 
-            let value = 0.5 + perlin.value_at(cgmath::Vector2::new(perlin_x, perlin_y));
+    let mut grid = Grid::from_vec(grid_vec, width, height);
+    grid.normalize(normalization);
+    grid
+}
 
-            if value < min {
-                min = value;
+/// Samples `noise` on a `dimensions` lattice starting at `origin`, with `spacing`
+/// between neighbouring points, without normalizing the values.
+///
+/// Lattice point `(x, y)` is always sampled at exactly `origin + (x, y) * spacing`,
+/// so grids whose origins lie on the same lattice agree on any points they share.
+pub fn sample_noise_grid<N>(
+    noise: &N,
+    origin: (i64, i64),
+    spacing: f64,
+    dimensions: (u32, u32),
+) -> Grid
+where
+    N: noise_lib::noise::Noise<IndexType = cgmath::Vector2<f64>>,
+{
+    let (width, height) = dimensions;
+    let mut grid_vec = Vec::with_capacity(width as usize * height as usize);
+
+    for y in 0..height {
+        let noise_y = (origin.1 + i64::from(y)) as f64 * spacing;
+        for x in 0..width {
+            let noise_x = (origin.0 + i64::from(x)) as f64 * spacing;
+            grid_vec.push(0.5 + noise.value_at(cgmath::Vector2::new(noise_x, noise_y)));
+        }
+    }
+
+    Grid::from_vec(grid_vec, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGULATIONS: &[Triangulation] = &[
+        Triangulation::Uniform,
+        Triangulation::Alternating,
+        Triangulation::Zigzag,
+        Triangulation::SlopeAware,
+    ];
+    const NORMAL_METHODS: &[NormalMethod] = &[
+        NormalMethod::FaceAverage,
+        NormalMethod::AreaWeighted,
+        NormalMethod::CentralDifference,
+        NormalMethod::Sobel,
+    ];
+
+    fn grid_from_fn<F: Fn(u32, u32) -> f64>(width: u32, height: u32, f: F) -> Grid {
+        let mut values = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                values.push(f(x, y));
+            }
+        }
+        Grid::from_vec(values, width, height)
+    }
+
+    fn bumpy(x: u32, y: u32) -> f64 {
+        f64::from((x * 7 + y * 3) % 5) * 0.2
+    }
+
+    /// The normal of a triangle the way the mesh winds it, which points up when the
+    /// triangle is wound consistently with the rest of the grid.
+    fn winding_normal(grid: &Grid, triangle: &[Index]) -> Vector3<f32> {
+        let (a, b, c) = (
+            grid.point(triangle[0]),
+            grid.point(triangle[1]),
+            grid.point(triangle[2]),
+        );
+        (c - a).cross(b - a)
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn triangulations_cover_every_cell_with_two_upward_triangles() {
+        let grid = grid_from_fn(6, 5, bumpy);
+        let flat = grid_from_fn(6, 5, |_, _| 0.0);
+        for &triangulation in TRIANGULATIONS {
+            let indices = grid.gen_index_buffer(triangulation);
+            assert_eq!(indices.len(), 5 * 4 * 6, "{}", triangulation);
+            assert!(indices.iter().all(|&i| i < 6 * 5));
+
+            // Flattened onto the xy plane, the triangles are wound the same way and
+            // add up to the area of the grid.
+            let mut area = 0.0;
+            for triangle in indices.chunks(3) {
+                let normal = winding_normal(&flat, triangle);
+                assert!(normal.z > 0.0, "{}: {:?}", triangulation, triangle);
+                area += normal.z / 2.0;
+            }
+            assert!((area - 20.0).abs() < 1e-4, "{}: {}", triangulation, area);
+        }
+    }
+
+    #[test]
+    fn triangulations_pick_their_diagonals() {
+        let grid = grid_from_fn(3, 3, bumpy);
+        let diagonals = |triangulation| {
+            let mut diagonals = Vec::new();
+            for y in 0..2 {
+                for x in 0..2 {
+                    diagonals.push(grid.uses_main_diagonal(triangulation, x, y));
+                }
             }
-            if value > max {
-                max = value;
+            diagonals
+        };
+        assert_eq!(
+            diagonals(Triangulation::Uniform),
+            [false, false, false, false]
+        );
+        assert_eq!(
+            diagonals(Triangulation::Alternating),
+            [false, true, true, false]
+        );
+        assert_eq!(diagonals(Triangulation::Zigzag), [false, false, true, true]);
+
+        // The main diagonal joins the two low corners, the other one a low and a high.
+        let valley = Grid::from_vec(vec![0.0, 1.0, 0.0, 0.0], 2, 2);
+        assert!(valley.uses_main_diagonal(Triangulation::SlopeAware, 0, 0));
+        let ridge = Grid::from_vec(vec![1.0, 0.0, 0.0, 0.0], 2, 2);
+        assert!(!ridge.uses_main_diagonal(Triangulation::SlopeAware, 0, 0));
+    }
+
+    #[test]
+    fn flat_grid_normals_point_up() {
+        let grid = grid_from_fn(5, 4, |_, _| 0.5);
+        for &triangulation in TRIANGULATIONS {
+            let indices = grid.gen_index_buffer(triangulation);
+            for &method in NORMAL_METHODS {
+                let normals = grid.compute_normals(method, &indices);
+                assert_eq!(normals.len(), 20);
+                for &normal in &normals {
+                    assert_close(normal, Vector3::new(0.0, 0.0, 1.0));
+                }
             }
+        }
+    }
 
-            grid_vec.push(value);
+    #[test]
+    fn sloped_grid_normals_lean_against_the_slope() {
+        let grid = grid_from_fn(6, 6, |x, y| 0.5 * f64::from(x) - 0.25 * f64::from(y));
+        let expected = Vector3::new(-0.5, 0.25, 1.0).normalize();
+        for &triangulation in TRIANGULATIONS {
+            let indices = grid.gen_index_buffer(triangulation);
+            for &method in NORMAL_METHODS {
+                let normals = grid.compute_normals(method, &indices);
+                // Sobel repeats the border values, which bends the normals along the
+                // edges, so only compare the inside of the grid.
+                for y in 1..5 {
+                    for x in 1..5 {
+                        assert_close(normals[(x + y * 6) as usize], expected);
+                    }
+                }
+            }
         }
     }
 
-    let scale = max - min;
-    let coeff = 1.0 / scale;
+    #[test]
+    fn flattened_meshes_give_each_face_its_own_vertices_and_normal() {
+        let grid = grid_from_fn(5, 4, bumpy);
+        let (vertices, indices) = grid.gen_vertex_buffer();
+        let (flat_vertices, flat_indices) = flatten_mesh(&vertices, &indices);
+
+        assert_eq!(flat_vertices.len(), indices.len());
+        assert_eq!(flat_indices, (0..indices.len() as Index).collect::<Vec<_>>());
 
-    //println!("{} {}", min, max);
-    let scaled_vec = grid_vec.iter().map(|x| (x - min) * coeff).collect();
-    Grid::from_vec(scaled_vec, width, height)
+        for (triangle, corners) in indices.chunks(3).zip(flat_vertices.chunks(3)) {
+            let expected = winding_normal(&grid, triangle).normalize();
+            for (&i, corner) in triangle.iter().zip(corners) {
+                assert_eq!(corner.position(), vertices[i as usize].position());
+                assert_close(Vector3::from(corner.normal()), expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,132 @@
+use image::{Rgba, RgbaImage};
+
+use color_ramp::ColorRamp;
+
+const BAR_WIDTH: u32 = 20;
+const BAR_HEIGHT: u32 = 200;
+const TICK_LENGTH: u32 = 4;
+const PADDING: u32 = 8;
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Glyphs are drawn with each of their pixels as a square of this size.
+const GLYPH_SCALE: u32 = 2;
+
+/// The labels of a colorbar, as heights and the text next to them.
+///
+/// The ends and the middle of the bar are labeled with their height and, if
+/// `raw_range` is given, in parentheses with the noise value it stands for. Heights
+/// go down as grid values go up, so the top of the bar is the low end of the range.
+pub fn labels(raw_range: Option<(f64, f64)>) -> Vec<(f32, String)> {
+    [1.0, 0.5, 0.0]
+        .iter()
+        .map(|&height: &f32| {
+            let text = match raw_range {
+                Some((min, max)) => {
+                    let raw = min + f64::from(1.0 - height) * (max - min);
+                    format!("{:.2} ({:.3})", height, raw)
+                }
+                None => format!("{:.2}", height),
+            };
+            (height, text)
+        })
+        .collect()
+}
+
+/// Draws a colorbar of `ramp` with height 0 at the bottom and 1 at the top, labeled
+/// with `labels`.
+pub fn draw_legend(ramp: &ColorRamp, labels: &[(f32, String)]) -> RgbaImage {
+    let advance = (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+    let text_width = labels
+        .iter()
+        .map(|&(_, ref text)| text.len() as u32 * advance)
+        .max()
+        .unwrap_or(0);
+    let text_height = GLYPH_HEIGHT * GLYPH_SCALE;
+
+    // Leave room for half a label above and below the bar.
+    let bar_left = PADDING;
+    let bar_top = PADDING + text_height / 2;
+    let text_left = bar_left + BAR_WIDTH + TICK_LENGTH + PADDING;
+    let mut img = RgbaImage::from_pixel(
+        text_left + text_width + PADDING,
+        bar_top * 2 + BAR_HEIGHT,
+        Rgba(BACKGROUND),
+    );
+
+    for y in 0..BAR_HEIGHT {
+        let height = 1.0 - y as f32 / (BAR_HEIGHT - 1) as f32;
+        let color = ramp.color_at(height);
+        let mut pixel = [0; 4];
+        for i in 0..4 {
+            pixel[i] = (color[i].max(0.0).min(1.0) * 255.0).round() as u8;
+        }
+        for x in 0..BAR_WIDTH {
+            img.put_pixel(bar_left + x, bar_top + y, Rgba(pixel));
+        }
+    }
+
+    for &(height, ref text) in labels {
+        let y = bar_top + ((1.0 - height) * (BAR_HEIGHT - 1) as f32).round() as u32;
+        for x in 0..TICK_LENGTH {
+            img.put_pixel(bar_left + BAR_WIDTH + x, y, Rgba(TEXT_COLOR));
+        }
+        draw_text(&mut img, text_left, y - text_height / 2, text);
+    }
+
+    img
+}
+
+/// Draws `text` with its top left corner at `(x, y)`, leaving out any characters
+/// the font doesn't have.
+fn draw_text(img: &mut RgbaImage, x: u32, y: u32, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let rows = match glyph(c) {
+            Some(rows) => rows,
+            None => continue,
+        };
+        let left = x + i as u32 * (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    for dx in 0..GLYPH_SCALE {
+                        img.put_pixel(
+                            left + col * GLYPH_SCALE + dx,
+                            y + row as u32 * GLYPH_SCALE + dy,
+                            Rgba(TEXT_COLOR),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The rows of a 5x7 glyph from the top, with the leftmost pixel as the highest bit.
+fn glyph(c: char) -> Option<[u8; 7]> {
+    let rows = match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        ' ' => [0x00; 7],
+        _ => return None,
+    };
+    Some(rows)
+}
@@ -1,105 +1,115 @@
 #![allow(dead_code)]
 
 extern crate cgmath;
+#[macro_use]
+extern crate clap;
 extern crate game_time;
+extern crate gif;
 #[macro_use]
 extern crate glium;
 extern crate image;
 extern crate noise_lib;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
 mod animated;
+mod assets;
+mod batch;
+mod builder;
 mod camera_controller;
+mod chunked;
+mod cli;
+mod color_ramp;
+mod config;
+mod error;
+mod export;
+mod map_view;
+mod procedural;
 mod render;
+mod scene;
+mod shader;
+mod share;
 mod geom;
+mod record;
+mod simplify;
+mod static_terrain;
+mod streaming;
 mod grid;
+mod legend;
 mod visualizer;
 mod uniform;
 
+use std::process;
+
 use glium::glutin;
 use cgmath::{Matrix4, Vector3};
-use geom::GeometryProvider;
 
-fn build_material_uniform(
-    vis: &visualizer::Visualizer,
-) -> glium::uniforms::UniformBuffer<visualizer::Materials> {
-    let materials = visualizer::Materials::new(&[
-        visualizer::Material {
-            ambient: [0.2, 0.2, 0.2, 1.0],
-            diffuse: [1.0, 1.0, 1.0, 1.0],
-            specular: [0.0, 0.0, 0.0, 1.0],
-            shine: 120.0,
-            _padding: Default::default(),
-        },
-        visualizer::Material {
-            ambient: [0.2, 0.2, 0.2, 1.0],
-            diffuse: [1.0, 1.0, 1.0, 1.0],
-            specular: [1.0, 1.0, 1.0, 1.0],
-            shine: 120.0,
-            _padding: Default::default(),
-        },
-    ]);
-
-    glium::uniforms::UniformBuffer::new(vis.display(), materials).unwrap()
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
 }
 
-fn build_geometry(
-    vis: &visualizer::Visualizer,
-) -> geom::Geometry<visualizer::Vertex, visualizer::Index> {
-    let rng = rand::StdRng::new().unwrap();
+fn run() -> error::Result<()> {
+    let args = cli::parse_args();
+    if let Some(ref job) = args.batch {
+        return batch::run(job);
+    }
+    let config = args.config;
+
+    if let Some(ref dir) = args.export_textures {
+        let paths = procedural::export_layer_textures(&config.layers, dir)?;
+        if paths.is_empty() {
+            println!("No layer has a procedural texture");
+        }
+        for path in paths {
+            println!("Saved {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(ref job) = args.render {
+        let mut vis = visualizer::Visualizer::new_headless()?;
+        show_config(&mut vis, &config)?;
+        let img = vis.render_image(job.size)?;
+        return img
+            .save(&job.path)
+            .map_err(|err| error::Error::Io(job.path.clone(), err));
+    }
+
+    if let Some(ref job) = args.record {
+        let mut vis = visualizer::Visualizer::new_headless()?;
+        show_config(&mut vis, &config)?;
+        return record::record(&mut vis, job);
+    }
 
-    let noise = noise_lib::perlin::build_geometric_octaves(
-        (2, 2),
-        10,
-        3.0,
-        &mut noise_lib::perlin::RandomGradientBuilder2d::new(rng),
-        &noise_lib::interpolate::ImprovedPerlinInterpolator::new(),
-    );
-
-    let grid = grid::make_noise_grid(&noise, (200, 200));
-    let (vertices, indices) = grid.gen_vertex_buffer();
-    let model = Matrix4::from_translation(Vector3::new(-50.0, -50.0, 20.0_f32))
-        * Matrix4::from_nonuniform_scale(1.0, 1.0, 100.0)
-        * Matrix4::from_scale(0.3333);
+    let window_builder = glutin::WindowBuilder::new()
+        .with_title("Noise Visualizer")
+        .with_dimensions(config.window_size.0, config.window_size.1);
+    let mut vis = visualizer::Visualizer::new(window_builder)?;
 
-    let vertex_buffer = glium::VertexBuffer::new(vis.display(), &vertices).unwrap();
-    let index_buffer = glium::IndexBuffer::new(
-        vis.display(),
-        glium::index::PrimitiveType::TrianglesList,
-        &indices,
-    ).unwrap();
+    show_config(&mut vis, &config)?;
+    if let Some(path) = args.scene_path {
+        vis.watch_scene(scene::SceneWatcher::new(path, args.overrides));
+    }
 
-    geom::Geometry::new(vertex_buffer, index_buffer, model)
+    vis.run()
 }
 
-fn build_perlin_animation() -> Box<GeometryProvider<visualizer::Vertex, visualizer::Index>> {
-    let rng = rand::StdRng::new().unwrap();
-
-    let noise = noise_lib::perlin3d::build_geometric_octaves(
-        (2, 2, 3),
-        8,
-        2.5,
-        &mut noise_lib::perlin3d::RandomGradientBuilder3d::new(rng),
-        &noise_lib::interpolate::ImprovedPerlinInterpolator::new(),
+/// Sets up the scene and view of `config`.
+fn show_config(vis: &mut visualizer::Visualizer, config: &config::Config) -> error::Result<()> {
+    vis.apply_config(config)?;
+    vis.set_camera_pose(
+        Vector3::from(config.camera.position),
+        config.camera.theta,
+        config.camera.phi,
+        config.camera.fov,
     );
-
-    Box::new(animated::PerlinAnimation::new(noise, (150, 150)))
-}
-
-fn main() {
-    let window_builder = glutin::WindowBuilder::new()
-        .with_title("Noise Visualizer")
-        .with_dimensions(1024, 768);
-    let mut vis = visualizer::Visualizer::new(window_builder);
-
-    //let geom = build_geometry(&vis);
-    let anim = build_perlin_animation();
-    let materials = build_material_uniform(&vis);
-    //vis.set_geometry(Box::new(geom));
-    vis.set_geometry(anim);
-    vis.set_materials(materials);
-
-    vis.run();
+    Ok(())
 }
 
 fn build_z_reflection_matrix() -> Matrix4<f32> {
@@ -0,0 +1,77 @@
+use std::f32;
+
+use cgmath::{self, Matrix4, Vector2};
+
+use geom::Bounds;
+
+/// A top-down orthographic view of the terrain, panned and zoomed with the mouse.
+///
+/// The view looks along the height axis with x to the right and y down, so the
+/// grid appears the same way as in exported heightmaps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapView {
+    /// The world position shown in the middle of the viewport.
+    center: Vector2<f32>,
+    /// World units per pixel.
+    scale: f32,
+}
+
+impl MapView {
+    pub fn new(center: Vector2<f32>, scale: f32) -> MapView {
+        assert!(scale > 0.0);
+        MapView { center, scale }
+    }
+
+    /// A view showing all of `bounds` in a viewport of `size` pixels.
+    pub fn fit(bounds: &Bounds, size: (u32, u32)) -> MapView {
+        let extent = bounds.size();
+        let scale = (extent.x / size.0.max(1) as f32)
+            .max(extent.y / size.1.max(1) as f32)
+            .max(f32::EPSILON);
+        let center = bounds.center();
+
+        // Leave a small margin around the terrain.
+        MapView::new(Vector2::new(center.x, center.y), scale * 1.05)
+    }
+
+    pub fn center(&self) -> Vector2<f32> {
+        self.center
+    }
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Moves the view along with a drag of `delta` pixels.
+    pub fn pan(&mut self, delta: (f64, f64)) {
+        self.center -= Vector2::new(delta.0 as f32, delta.1 as f32) * self.scale;
+    }
+
+    /// Zooms in for a `factor` above 1, keeping the point under `cursor` in place.
+    pub fn zoom(&mut self, factor: f32, cursor: (f64, f64), size: (u32, u32)) {
+        let offset = Vector2::new(
+            cursor.0 as f32 - size.0 as f32 / 2.0,
+            cursor.1 as f32 - size.1 as f32 / 2.0,
+        );
+        let anchor = self.center + offset * self.scale;
+
+        self.scale /= factor;
+        self.center = anchor - offset * self.scale;
+    }
+
+    /// The projection of world coordinates into a viewport of `size` pixels.
+    pub fn matrix(&self, size: (u32, u32)) -> Matrix4<f32> {
+        let half_width = size.0 as f32 / 2.0 * self.scale;
+        let half_height = size.1 as f32 / 2.0 * self.scale;
+
+        // Bottom and top are swapped to have y pointing down. The depth range only
+        // needs to contain the terrain, since the map is drawn without depth testing.
+        cgmath::ortho(
+            self.center.x - half_width,
+            self.center.x + half_width,
+            self.center.y + half_height,
+            self.center.y - half_height,
+            -1.0e5,
+            1.0e5,
+        )
+    }
+}
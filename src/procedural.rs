@@ -0,0 +1,299 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use cgmath::Vector2;
+use image::{Rgba, RgbaImage};
+use noise_lib;
+use noise_lib::noise::Noise;
+
+use builder;
+use color_ramp::{ColorRamp, ColorStop};
+use config::{LayerConfig, TextureSource};
+use error::{self, Error};
+use grid::{Grid, Normalization};
+
+/// A texture synthesized from 2D noise, which tiles seamlessly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProceduralTexture {
+    /// Names the files the texture is exported to.
+    pub name: String,
+    /// Width and height in pixels.
+    pub size: u32,
+    /// Lattice size of the first octave across the texture.
+    pub cells: u32,
+    pub octaves: u32,
+    /// Amplitude ratio between successive octaves.
+    pub persistence: f64,
+    pub seed: u64,
+    /// Colors by noise value, from 0 for the lowest value in the texture to 1 for
+    /// the highest.
+    pub colors: ColorRamp,
+}
+
+impl ProceduralTexture {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size < 2 {
+            return Err("procedural textures must be at least 2 pixels wide".to_string());
+        }
+        if self.cells == 0 {
+            return Err("procedural textures need at least 1 cell".to_string());
+        }
+        if self.octaves == 0 {
+            return Err("the number of octaves must be greater than 0".to_string());
+        }
+        if !(self.persistence > 0.0 && self.persistence.is_finite()) {
+            return Err(format!(
+                "the persistence must be a positive number, got {}",
+                self.persistence
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn generate(&self) -> RgbaImage {
+        // Each pixel blends four samples from a quarter of the noise, offset by half
+        // of it in x and y, weighted so that opposite edges get the same samples.
+        let lattice = self.cells * 2;
+        let noise = noise_lib::perlin::build_geometric_octaves(
+            (lattice, lattice),
+            self.octaves,
+            self.persistence,
+            &mut noise_lib::perlin::RandomGradientBuilder2d::new(builder::make_rng(self.seed)),
+            &noise_lib::interpolate::ImprovedPerlinInterpolator::new(),
+        );
+
+        let size = f64::from(self.size);
+        let mut values = Vec::with_capacity(self.size as usize * self.size as usize);
+        for y in 0..self.size {
+            let v = f64::from(y) / size;
+            for x in 0..self.size {
+                let u = f64::from(x) / size;
+                let sample = |du: f64, dv: f64| {
+                    noise.value_at(Vector2::new(u / 2.0 + du, v / 2.0 + dv))
+                };
+                values.push(
+                    (1.0 - u) * (1.0 - v) * sample(0.5, 0.5)
+                        + u * (1.0 - v) * sample(0.0, 0.5)
+                        + (1.0 - u) * v * sample(0.5, 0.0)
+                        + u * v * sample(0.0, 0.0),
+                );
+            }
+        }
+        // The blend flattens the noise towards the middle, so stretch it back out.
+        let mut grid = Grid::from_vec(values, self.size, self.size);
+        grid.normalize(Normalization::MinMax);
+
+        RgbaImage::from_fn(self.size, self.size, |x, y| {
+            let color = self.colors.color_at(grid.value(x, y) as f32);
+            let mut pixel = [0; 4];
+            for i in 0..4 {
+                pixel[i] = (color[i].max(0.0).min(1.0) * 255.0).round() as u8;
+            }
+            Rgba(pixel)
+        })
+    }
+}
+
+/// The procedural textures that come with the visualizer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TexturePreset {
+    Grass,
+    /// Gray stone with dark cracks.
+    Rock,
+    Sand,
+    Snow,
+    /// Deep blue with lighter ripples.
+    Water,
+}
+
+impl TexturePreset {
+    pub const NAMES: &'static [&'static str] = &["grass", "rock", "sand", "snow", "water"];
+
+    pub fn texture(&self) -> ProceduralTexture {
+        let (cells, octaves, persistence, seed, stops): (_, _, _, _, &[(f32, [f32; 4])]) =
+            match *self {
+                TexturePreset::Grass => (
+                    16,
+                    4,
+                    2.0,
+                    1,
+                    &[
+                        (0.0, [0.10, 0.25, 0.05, 1.0]),
+                        (0.5, [0.22, 0.45, 0.10, 1.0]),
+                        (1.0, [0.45, 0.62, 0.20, 1.0]),
+                    ],
+                ),
+                TexturePreset::Rock => (
+                    4,
+                    6,
+                    2.0,
+                    2,
+                    &[
+                        (0.0, [0.18, 0.17, 0.16, 1.0]),
+                        (0.25, [0.38, 0.36, 0.34, 1.0]),
+                        (0.6, [0.52, 0.50, 0.47, 1.0]),
+                        (1.0, [0.70, 0.68, 0.64, 1.0]),
+                    ],
+                ),
+                TexturePreset::Sand => (
+                    32,
+                    3,
+                    3.0,
+                    3,
+                    &[
+                        (0.0, [0.72, 0.60, 0.40, 1.0]),
+                        (1.0, [0.90, 0.80, 0.60, 1.0]),
+                    ],
+                ),
+                TexturePreset::Snow => (
+                    4,
+                    4,
+                    3.0,
+                    4,
+                    &[
+                        (0.0, [0.80, 0.85, 0.92, 1.0]),
+                        (1.0, [1.0, 1.0, 1.0, 1.0]),
+                    ],
+                ),
+                TexturePreset::Water => (
+                    8,
+                    4,
+                    2.0,
+                    5,
+                    &[
+                        (0.0, [0.05, 0.18, 0.32, 1.0]),
+                        (0.6, [0.10, 0.30, 0.48, 1.0]),
+                        (1.0, [0.25, 0.50, 0.65, 1.0]),
+                    ],
+                ),
+            };
+
+        let stops = stops
+            .iter()
+            .map(|&(height, color)| ColorStop { height, color })
+            .collect();
+        let name = self.to_string();
+        ProceduralTexture {
+            colors: ColorRamp::new(&name, stops).unwrap(),
+            name,
+            size: 256,
+            cells,
+            octaves,
+            persistence,
+            seed,
+        }
+    }
+}
+
+impl FromStr for TexturePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TexturePreset, String> {
+        match s {
+            "grass" => Ok(TexturePreset::Grass),
+            "rock" => Ok(TexturePreset::Rock),
+            "sand" => Ok(TexturePreset::Sand),
+            "snow" => Ok(TexturePreset::Snow),
+            "water" => Ok(TexturePreset::Water),
+            _ => Err(format!("unknown texture preset '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for TexturePreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            TexturePreset::Grass => "grass",
+            TexturePreset::Rock => "rock",
+            TexturePreset::Sand => "sand",
+            TexturePreset::Snow => "snow",
+            TexturePreset::Water => "water",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Writes the procedural textures of `layers` to `dir` as `layer<index>_<name>.png`,
+/// and returns the paths written.
+pub fn export_layer_textures(
+    layers: &[LayerConfig],
+    dir: &Path,
+) -> error::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).map_err(|err| Error::Io(dir.to_path_buf(), err))?;
+
+    let mut paths = Vec::new();
+    for (i, layer) in layers.iter().enumerate() {
+        if let TextureSource::Procedural(ref texture) = layer.texture {
+            let path = dir.join(format!("layer{}_{}.png", i, texture.name));
+            texture
+                .generate()
+                .save(&path)
+                .map_err(|err| Error::Io(path.clone(), err))?;
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_texture(seed: u64) -> ProceduralTexture {
+        ProceduralTexture {
+            size: 64,
+            seed,
+            ..TexturePreset::Rock.texture()
+        }
+    }
+
+    /// The largest difference of any channel between two pixels.
+    fn difference(a: &Rgba<u8>, b: &Rgba<u8>) -> u8 {
+        a.data
+            .iter()
+            .zip(&b.data)
+            .map(|(&a, &b)| if a > b { a - b } else { b - a })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn a_seed_always_gives_the_same_texture() {
+        let pixels = |seed| small_texture(seed).generate().into_raw();
+        assert_eq!(pixels(7), pixels(7));
+        assert!(pixels(7) != pixels(8));
+    }
+
+    #[test]
+    fn textures_tile_seamlessly() {
+        let img = small_texture(3).generate();
+        let size = img.width();
+
+        // Neighbours across the edges of a tile differ no more than neighbours inside.
+        let mut inside = 0;
+        let mut across = 0;
+        for i in 0..size {
+            for j in 0..(size - 1) {
+                inside = inside
+                    .max(difference(img.get_pixel(j, i), img.get_pixel(j + 1, i)))
+                    .max(difference(img.get_pixel(i, j), img.get_pixel(i, j + 1)));
+            }
+            across = across
+                .max(difference(img.get_pixel(size - 1, i), img.get_pixel(0, i)))
+                .max(difference(img.get_pixel(i, size - 1), img.get_pixel(i, 0)));
+        }
+        assert!(inside > 0);
+        assert!(across <= inside, "{} across, {} inside", across, inside);
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for name in TexturePreset::NAMES {
+            let preset = TexturePreset::from_str(name).unwrap();
+            assert_eq!(preset.to_string(), *name);
+            assert_eq!(preset.texture().validate(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::f32;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+use cgmath::{InnerSpace, Vector3};
+use gif::{self, SetParameter};
+
+use error::{self, Error};
+use geom::Bounds;
+use visualizer::Visualizer;
+
+/// Frames rendered at a fixed timestep, independently of how long each one takes.
+pub struct RecordJob {
+    /// Directory the frames are written to, as `frame_00000.png` and so on.
+    pub dir: PathBuf,
+    pub size: (u32, u32),
+    pub fps: f64,
+    pub frames: u32,
+    /// Also writes the frames as a looping animated GIF.
+    pub gif: Option<PathBuf>,
+    /// Moves the camera around the terrain over the recording; otherwise the camera
+    /// stays where it is.
+    pub orbit: Option<Orbit>,
+}
+
+/// A circle traced by the camera around the center of the terrain, looking at it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    /// Distance from the center, or `None` for the diagonal of the terrain bounds.
+    pub radius: Option<f32>,
+    /// Angle above the plane of the terrain, in degrees.
+    pub elevation: f32,
+    /// Number of times the camera goes around over the recording.
+    pub revolutions: f32,
+}
+
+impl Orbit {
+    /// The position of the camera after `t` of the recording, from 0 to 1.
+    pub fn position(&self, bounds: &Bounds, t: f32) -> Vector3<f32> {
+        let radius = self.radius.unwrap_or_else(|| bounds.size().magnitude());
+        let angle = 2.0 * f32::consts::PI * self.revolutions * t;
+        let elevation = self.elevation.to_radians();
+
+        // The terrain rises towards -z.
+        bounds.center()
+            + Vector3::new(
+                elevation.cos() * angle.cos(),
+                elevation.cos() * angle.sin(),
+                -elevation.sin(),
+            ) * radius
+    }
+}
+
+/// Renders the frames of `job` offscreen with `vis`, from time 0 onwards.
+pub fn record(vis: &mut Visualizer, job: &RecordJob) -> error::Result<()> {
+    fs::create_dir_all(&job.dir).map_err(|err| Error::Io(job.dir.clone(), err))?;
+
+    // The orbit goes around the terrain as it is at the start of the recording.
+    let orbit_bounds = match job.orbit {
+        Some(_) => {
+            vis.load_geometry()?;
+            let bounds = vis.bounds().ok_or_else(|| {
+                Error::Invalid("there is no terrain to orbit around".to_string())
+            })?;
+            Some(bounds)
+        }
+        None => None,
+    };
+
+    let (width, height) = job.size;
+    let mut gif_encoder = match job.gif {
+        Some(ref path) => {
+            if width > u32::from(u16::max_value()) || height > u32::from(u16::max_value()) {
+                return Err(Error::Invalid(
+                    "GIF frames can't be larger than 65535x65535".to_string(),
+                ));
+            }
+            // GIF delays are in hundredths of a second, and viewers replace a delay of
+            // 0 with one of their own.
+            if job.fps > 100.0 {
+                return Err(Error::Invalid(format!(
+                    "GIFs can't play more than 100 frames per second, got {}",
+                    job.fps
+                )));
+            }
+            let gif_error = |err: io::Error| Error::Io(path.clone(), err);
+            let file = File::create(path).map_err(&gif_error)?;
+            let mut encoder =
+                gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+                    .map_err(&gif_error)?;
+            encoder.set(gif::Repeat::Infinite).map_err(&gif_error)?;
+            Some((path, encoder))
+        }
+        None => None,
+    };
+    // The time each frame is shown at, in hundredths of a second. Rounding the times
+    // rather than the delays keeps the rounding errors from adding up, so that 60 fps
+    // alternates between delays of 2 and 1 instead of playing at 50 fps.
+    let gif_time = |i: u32| (f64::from(i) * 100.0 / job.fps).round() as u64;
+
+    for i in 0..job.frames {
+        vis.set_time(f64::from(i) / job.fps);
+        if let (Some(orbit), Some(bounds)) = (job.orbit, orbit_bounds) {
+            let position = orbit.position(&bounds, i as f32 / job.frames as f32);
+            vis.look_at(position, bounds.center(), Vector3::new(0.0, 0.0, -1.0));
+        }
+        let img = vis.render_image(job.size)?;
+
+        let path = job.dir.join(format!("frame_{:05}.png", i));
+        img.save(&path).map_err(|err| Error::Io(path, err))?;
+
+        if let Some((gif_path, ref mut encoder)) = gif_encoder {
+            let mut pixels = img.into_raw();
+            let mut frame = gif::Frame::from_rgba(width as u16, height as u16, &mut pixels);
+            frame.delay = (gif_time(i + 1) - gif_time(i)) as u16;
+            encoder
+                .write_frame(&frame)
+                .map_err(|err| Error::Io(gif_path.clone(), err))?;
+        }
+
+        println!("Recorded frame {}/{}", i + 1, job.frames);
+    }
+    Ok(())
+}
@@ -0,0 +1,831 @@
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use toml;
+
+use color_ramp::{ColorRamp, ColorStop, RampPreset};
+use config::{Config, ConfigOverrides, InterpolatorKind, LayerConfig, MaterialConfig, Mode,
+             TextureSource};
+use grid::{NormalMethod, Normalization, Shading, Triangulation};
+use procedural::{ProceduralTexture, TexturePreset};
+use shader::ShaderMode;
+
+/// A scene description as stored in a TOML file.
+///
+/// Every entry is optional; missing ones keep the defaults of the scene's mode.
+/// For example:
+///
+/// ```toml
+/// mode = "static"
+///
+/// [noise]
+/// dimensions = [2, 2]
+/// octaves = 10
+/// persistence = 3.0
+/// interpolator = "improved"
+/// seed = 1234
+///
+/// [grid]
+/// size = [200, 200]
+/// normalization = "minmax"
+/// shading = "smooth"
+/// triangulation = "uniform"
+/// normals = "face-average"
+/// max_error = 0.01
+/// translation = [-50.0, -50.0, 20.0]
+/// scale = [0.3333, 0.3333, 33.33]
+///
+/// [[materials]]
+/// ambient = [0.2, 0.2, 0.2, 1.0]
+/// diffuse = [1.0, 1.0, 1.0, 1.0]
+/// specular = [0.0, 0.0, 0.0, 1.0]
+/// shine = 120.0
+///
+/// [light]
+/// direction = [100.0, 0.0, -500.0]
+/// color = [1.0, 1.0, 1.0, 1.0]
+///
+/// [[layers]]
+/// texture = "Assets/water_texture_1.jpg"
+///
+/// [[layers]]
+/// texture = "Assets/grass_texture_1.jpg"
+/// height = [0.3, 1.0]
+/// slope = [0.0, 90.0]
+/// scale = 4.0
+/// blend = 0.1
+///
+/// [assets]
+/// dirs = ["textures"]
+///
+/// [colors]
+/// ramp = "terrain"
+///
+/// [camera]
+/// position = [0.0, 0.0, 0.0]
+/// fov = 45.0
+///
+/// [render]
+/// shader = "per-pixel"
+/// wireframe = false
+/// triplanar = true
+/// slope_layers = true
+/// ```
+///
+/// Relative texture paths and asset directories are resolved against the directory
+/// of the scene file. Textures that aren't found at their path are searched for in
+/// the asset directories.
+///
+/// Instead of a file, a layer can have a texture generated from noise, starting
+/// from one of the `TexturePreset`s. With stops of its own and no preset, the
+/// remaining entries default to those of the grass preset:
+///
+/// ```toml
+/// [[layers]]
+/// height = [0.85, 1.0]
+///
+/// [layers.procedural]
+/// preset = "snow"
+/// size = 256
+/// cells = 4
+/// octaves = 4
+/// persistence = 3.0
+/// seed = 4
+/// ```
+///
+/// The `[colors]` section colors the terrain by height instead of with textures,
+/// either with one of the `RampPreset`s or with stops of its own:
+///
+/// ```toml
+/// [colors]
+/// ramp = "lava"
+///
+/// [[colors.stops]]
+/// height = 0.0
+/// color = [0.1, 0.0, 0.0, 1.0]
+///
+/// [[colors.stops]]
+/// height = 1.0
+/// color = [1.0, 0.8, 0.2, 1.0]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneFile {
+    pub mode: Option<String>,
+    pub window: Option<[u32; 2]>,
+    #[serde(default)]
+    pub noise: NoiseSection,
+    #[serde(default)]
+    pub grid: GridSection,
+    pub materials: Option<Vec<MaterialSection>>,
+    #[serde(default)]
+    pub light: LightSection,
+    pub layers: Option<Vec<LayerSection>>,
+    #[serde(default)]
+    pub assets: AssetSection,
+    #[serde(default)]
+    pub colors: ColorSection,
+    #[serde(default)]
+    pub camera: CameraSection,
+    #[serde(default)]
+    pub render: RenderSection,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseSection {
+    pub dimensions: Option<Vec<u32>>,
+    pub octaves: Option<u32>,
+    pub persistence: Option<f64>,
+    pub interpolator: Option<String>,
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridSection {
+    pub size: Option<[u32; 2]>,
+    pub normalization: Option<String>,
+    pub shading: Option<String>,
+    pub triangulation: Option<String>,
+    pub normals: Option<String>,
+    pub max_error: Option<f64>,
+    pub translation: Option<[f32; 3]>,
+    pub scale: Option<[f32; 3]>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialSection {
+    pub ambient: [f32; 4],
+    pub diffuse: [f32; 4],
+    pub specular: [f32; 4],
+    pub shine: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightSection {
+    pub direction: Option<[f32; 3]>,
+    pub color: Option<[f32; 4]>,
+}
+
+/// A texture layer, with either a `texture` file or a `procedural` one; the entries
+/// left out cover the whole terrain, as in `LayerConfig::new`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerSection {
+    pub texture: Option<PathBuf>,
+    pub height: Option<[f32; 2]>,
+    pub slope: Option<[f32; 2]>,
+    pub scale: Option<f32>,
+    pub blend: Option<f32>,
+    pub procedural: Option<ProceduralSection>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProceduralSection {
+    /// The name of a preset, or of the texture given by `stops`.
+    pub preset: Option<String>,
+    pub size: Option<u32>,
+    pub cells: Option<u32>,
+    pub octaves: Option<u32>,
+    pub persistence: Option<f64>,
+    pub seed: Option<u64>,
+    pub stops: Option<Vec<ColorStopSection>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetSection {
+    pub dirs: Option<Vec<PathBuf>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorSection {
+    /// The name of a preset, or of the ramp given by `stops`.
+    pub ramp: Option<String>,
+    pub stops: Option<Vec<ColorStopSection>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorStopSection {
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraSection {
+    pub position: Option<[f32; 3]>,
+    pub theta: Option<f32>,
+    pub phi: Option<f32>,
+    pub fov: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderSection {
+    pub shader: Option<String>,
+    pub wireframe: Option<bool>,
+    pub triplanar: Option<bool>,
+    pub slope_layers: Option<bool>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneError::Io(ref path, ref err) => {
+                write!(f, "could not read scene file {}: {}", path.display(), err)
+            }
+            SceneError::Parse(ref path, ref err) => {
+                write!(f, "could not parse scene file {}: {}", path.display(), err)
+            }
+            SceneError::Invalid(ref path, ref msg) => {
+                write!(f, "invalid scene file {}: {}", path.display(), msg)
+            }
+        }
+    }
+}
+
+impl error::Error for SceneError {
+    fn description(&self) -> &str {
+        match *self {
+            SceneError::Io(..) => "could not read scene file",
+            SceneError::Parse(..) => "could not parse scene file",
+            SceneError::Invalid(..) => "invalid scene file",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SceneError::Io(_, ref err) => Some(err),
+            SceneError::Parse(_, ref err) => Some(err),
+            SceneError::Invalid(..) => None,
+        }
+    }
+}
+
+impl SceneFile {
+    /// Reads and parses a scene file, resolving relative texture paths and asset
+    /// directories against the directory containing it.
+    pub fn load(path: &Path) -> Result<SceneFile, SceneError> {
+        let mut text = String::new();
+        fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| SceneError::Io(path.to_path_buf(), err))?;
+
+        let mut scene =
+            SceneFile::parse(&text).map_err(|err| SceneError::Parse(path.to_path_buf(), err))?;
+
+        if let Some(dir) = path.parent() {
+            let textures = scene
+                .layers
+                .iter_mut()
+                .flat_map(|layers| layers.iter_mut().filter_map(|layer| layer.texture.as_mut()));
+            let asset_dirs = scene.assets.dirs.iter_mut().flat_map(|dirs| dirs.iter_mut());
+            for path in textures.chain(asset_dirs) {
+                if path.is_relative() {
+                    *path = dir.join(&path);
+                }
+            }
+        }
+        Ok(scene)
+    }
+
+    pub fn parse(text: &str) -> Result<SceneFile, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Describes every value of `config`, so loading the result gives `config` back.
+    ///
+    /// Relative texture paths and asset directories are made absolute against the
+    /// working directory, so they still point at the same files wherever the scene
+    /// file is saved.
+    pub fn from_config(config: &Config) -> SceneFile {
+        SceneFile {
+            mode: Some(config.mode.to_string()),
+            window: Some([config.window_size.0, config.window_size.1]),
+            noise: NoiseSection {
+                dimensions: Some(config.noise.dimensions.clone()),
+                octaves: Some(config.noise.octaves),
+                persistence: Some(config.noise.persistence),
+                interpolator: Some(config.noise.interpolator.to_string()),
+                seed: config.noise.seed,
+            },
+            grid: GridSection {
+                size: Some([config.grid_size.0, config.grid_size.1]),
+                normalization: Some(config.normalization.to_string()),
+                shading: Some(config.shading.to_string()),
+                triangulation: Some(config.mesh.triangulation.to_string()),
+                normals: Some(config.mesh.normals.to_string()),
+                max_error: config.max_error,
+                translation: Some(config.model.translation),
+                scale: Some(config.model.scale),
+            },
+            materials: Some(
+                config
+                    .materials
+                    .iter()
+                    .map(|m| MaterialSection {
+                        ambient: m.ambient,
+                        diffuse: m.diffuse,
+                        specular: m.specular,
+                        shine: m.shine,
+                    })
+                    .collect(),
+            ),
+            light: LightSection {
+                direction: Some(config.light.direction),
+                color: Some(config.light.color),
+            },
+            layers: Some(
+                config
+                    .layers
+                    .iter()
+                    .map(|layer| {
+                        let (texture, procedural) = match layer.texture {
+                            TextureSource::File(ref path) => (Some(absolute_path(path)), None),
+                            TextureSource::Procedural(ref texture) => {
+                                (None, Some(ProceduralSection::from_texture(texture)))
+                            }
+                        };
+                        LayerSection {
+                            texture,
+                            height: Some(layer.height),
+                            slope: Some(layer.slope),
+                            scale: Some(layer.scale),
+                            blend: Some(layer.blend),
+                            procedural,
+                        }
+                    })
+                    .collect(),
+            ),
+            assets: AssetSection {
+                dirs: Some(config.asset_dirs.iter().map(|dir| absolute_path(dir)).collect()),
+            },
+            colors: match config.color_ramp {
+                Some(ref ramp) => ColorSection {
+                    ramp: Some(ramp.name().to_string()),
+                    stops: Some(
+                        ramp.stops()
+                            .iter()
+                            .map(|stop| ColorStopSection {
+                                height: stop.height,
+                                color: stop.color,
+                            })
+                            .collect(),
+                    ),
+                },
+                None => ColorSection::default(),
+            },
+            camera: CameraSection {
+                position: Some(config.camera.position),
+                theta: Some(config.camera.theta),
+                phi: Some(config.camera.phi),
+                fov: Some(config.camera.fov),
+            },
+            render: RenderSection {
+                shader: Some(config.shader_mode.to_string()),
+                wireframe: Some(config.wireframe),
+                triplanar: Some(config.triplanar),
+                slope_layers: Some(config.slope_layers),
+            },
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("scene files only contain TOML-compatible values")
+    }
+
+    pub fn mode(&self) -> Result<Option<Mode>, String> {
+        match self.mode {
+            Some(ref mode) => Mode::from_str(mode)
+                .map(Some)
+                .map_err(|err| format!("mode: {}", err)),
+            None => Ok(None),
+        }
+    }
+
+    /// Overrides the values of `config` with the ones present in the scene.
+    ///
+    /// The result isn't validated as a whole, which is left to `Config::validate`.
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        if let Some(window) = self.window {
+            config.window_size = (window[0], window[1]);
+        }
+
+        let noise = &self.noise;
+        if let Some(ref dimensions) = noise.dimensions {
+            config.noise.dimensions = dimensions.clone();
+        }
+        if let Some(octaves) = noise.octaves {
+            config.noise.octaves = octaves;
+        }
+        if let Some(persistence) = noise.persistence {
+            config.noise.persistence = persistence;
+        }
+        if let Some(ref interpolator) = noise.interpolator {
+            config.noise.interpolator = InterpolatorKind::from_str(interpolator)
+                .map_err(|err| format!("noise.interpolator: {}", err))?;
+        }
+        if noise.seed.is_some() {
+            config.noise.seed = noise.seed;
+        }
+
+        if let Some(size) = self.grid.size {
+            config.grid_size = (size[0], size[1]);
+        }
+        if let Some(ref normalization) = self.grid.normalization {
+            config.normalization = Normalization::from_str(normalization)
+                .map_err(|err| format!("grid.normalization: {}", err))?;
+        }
+        if let Some(ref shading) = self.grid.shading {
+            config.shading = Shading::from_str(shading)
+                .map_err(|err| format!("grid.shading: {}", err))?;
+        }
+        if let Some(ref triangulation) = self.grid.triangulation {
+            config.mesh.triangulation = Triangulation::from_str(triangulation)
+                .map_err(|err| format!("grid.triangulation: {}", err))?;
+        }
+        if let Some(ref normals) = self.grid.normals {
+            config.mesh.normals = NormalMethod::from_str(normals)
+                .map_err(|err| format!("grid.normals: {}", err))?;
+        }
+        if self.grid.max_error.is_some() {
+            config.max_error = self.grid.max_error;
+        }
+        if let Some(translation) = self.grid.translation {
+            config.model.translation = translation;
+        }
+        if let Some(scale) = self.grid.scale {
+            config.model.scale = scale;
+        }
+
+        if let Some(ref materials) = self.materials {
+            config.materials = materials
+                .iter()
+                .map(|m| MaterialConfig {
+                    ambient: m.ambient,
+                    diffuse: m.diffuse,
+                    specular: m.specular,
+                    shine: m.shine,
+                })
+                .collect();
+        }
+
+        if let Some(direction) = self.light.direction {
+            config.light.direction = direction;
+        }
+        if let Some(color) = self.light.color {
+            config.light.color = color;
+        }
+
+        if let Some(ref layers) = self.layers {
+            config.layers = layers
+                .iter()
+                .map(|layer| {
+                    let texture = match (&layer.texture, &layer.procedural) {
+                        (&Some(ref path), &None) => TextureSource::File(path.clone()),
+                        (&None, &Some(ref procedural)) => TextureSource::Procedural(
+                            procedural
+                                .texture()
+                                .map_err(|err| format!("layers.procedural: {}", err))?,
+                        ),
+                        _ => {
+                            return Err(
+                                "layers: each layer needs either a texture or procedural"
+                                    .to_string(),
+                            )
+                        }
+                    };
+                    let defaults = LayerConfig::new(texture);
+                    Ok(LayerConfig {
+                        height: layer.height.unwrap_or(defaults.height),
+                        slope: layer.slope.unwrap_or(defaults.slope),
+                        scale: layer.scale.unwrap_or(defaults.scale),
+                        blend: layer.blend.unwrap_or(defaults.blend),
+                        ..defaults
+                    })
+                })
+                .collect::<Result<_, String>>()?;
+        }
+
+        if let Some(ref dirs) = self.assets.dirs {
+            config.asset_dirs = dirs.clone();
+        }
+
+        if let Some(ramp) = self.colors.color_ramp()? {
+            config.color_ramp = Some(ramp);
+        }
+
+        let camera = &self.camera;
+        if let Some(position) = camera.position {
+            config.camera.position = position;
+        }
+        if let Some(theta) = camera.theta {
+            config.camera.theta = theta;
+        }
+        if let Some(phi) = camera.phi {
+            config.camera.phi = phi;
+        }
+        if let Some(fov) = camera.fov {
+            config.camera.fov = fov;
+        }
+
+        if let Some(ref shader) = self.render.shader {
+            config.shader_mode = ShaderMode::from_str(shader)
+                .map_err(|err| format!("render.shader: {}", err))?;
+        }
+        if let Some(wireframe) = self.render.wireframe {
+            config.wireframe = wireframe;
+        }
+        if let Some(triplanar) = self.render.triplanar {
+            config.triplanar = triplanar;
+        }
+        if let Some(slope_layers) = self.render.slope_layers {
+            config.slope_layers = slope_layers;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProceduralSection {
+    fn from_texture(texture: &ProceduralTexture) -> ProceduralSection {
+        ProceduralSection {
+            preset: Some(texture.name.clone()),
+            size: Some(texture.size),
+            cells: Some(texture.cells),
+            octaves: Some(texture.octaves),
+            persistence: Some(texture.persistence),
+            seed: Some(texture.seed),
+            stops: Some(
+                texture
+                    .colors
+                    .stops()
+                    .iter()
+                    .map(|stop| ColorStopSection {
+                        height: stop.height,
+                        color: stop.color,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn texture(&self) -> Result<ProceduralTexture, String> {
+        let mut texture = match (&self.preset, &self.stops) {
+            (&Some(ref name), &None) => TexturePreset::from_str(name)?.texture(),
+            (name, _) => {
+                let mut texture = TexturePreset::Grass.texture();
+                texture.name = name.clone().unwrap_or_else(|| "custom".to_string());
+                texture
+            }
+        };
+        if let Some(ref stops) = self.stops {
+            let stops = stops
+                .iter()
+                .map(|stop| ColorStop {
+                    height: stop.height,
+                    color: stop.color,
+                })
+                .collect();
+            texture.colors = ColorRamp::new(&texture.name, stops)?;
+        }
+        if let Some(size) = self.size {
+            texture.size = size;
+        }
+        if let Some(cells) = self.cells {
+            texture.cells = cells;
+        }
+        if let Some(octaves) = self.octaves {
+            texture.octaves = octaves;
+        }
+        if let Some(persistence) = self.persistence {
+            texture.persistence = persistence;
+        }
+        if let Some(seed) = self.seed {
+            texture.seed = seed;
+        }
+        Ok(texture)
+    }
+}
+
+impl ColorSection {
+    fn color_ramp(&self) -> Result<Option<ColorRamp>, String> {
+        match (&self.ramp, &self.stops) {
+            (name, &Some(ref stops)) => {
+                let stops = stops
+                    .iter()
+                    .map(|stop| ColorStop {
+                        height: stop.height,
+                        color: stop.color,
+                    })
+                    .collect();
+                let name = name.as_ref().map_or("custom", |name| name.as_str());
+                ColorRamp::new(name, stops)
+                    .map(Some)
+                    .map_err(|err| format!("colors.stops: {}", err))
+            }
+            (&Some(ref name), &None) => RampPreset::from_str(name)
+                .map(|preset| Some(preset.ramp()))
+                .map_err(|err| format!("colors.ramp: {}", err)),
+            (&None, &None) => Ok(None),
+        }
+    }
+}
+
+/// Loads the configuration described by a scene file on top of the defaults of
+/// its mode, or of `Mode::Static` if it doesn't name one, and then applies
+/// `overrides`.
+pub fn load_config(path: &Path, overrides: &ConfigOverrides) -> Result<Config, SceneError> {
+    let scene = SceneFile::load(path)?;
+    let invalid = |msg| SceneError::Invalid(path.to_path_buf(), msg);
+
+    let mode = match overrides.mode {
+        Some(mode) => mode,
+        None => scene.mode().map_err(&invalid)?.unwrap_or(Mode::Static),
+    };
+    let mut config = Config::for_mode(mode);
+    scene.apply(&mut config).map_err(&invalid)?;
+    overrides.apply(&mut config);
+    config.validate().map_err(&invalid)?;
+
+    Ok(config)
+}
+
+/// Polls the modification time of a scene file to reload it when it changes.
+pub struct SceneWatcher {
+    path: PathBuf,
+    overrides: ConfigOverrides,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl SceneWatcher {
+    pub fn new(path: PathBuf, overrides: ConfigOverrides) -> SceneWatcher {
+        let modified = modification_time(&path);
+        SceneWatcher {
+            path,
+            overrides,
+            modified,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reloads the configuration if the file was modified since the last reload.
+    ///
+    /// The file is checked at most every `POLL_INTERVAL_MS`, so this can be called
+    /// every frame. Returns `None` if nothing changed.
+    pub fn poll(&mut self) -> Option<Result<Config, SceneError>> {
+        if self.last_poll.elapsed() < Duration::from_millis(POLL_INTERVAL_MS) {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = modification_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(load_config(&self.path, &self.overrides))
+    }
+}
+
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// `path` joined to the working directory if it is relative, without the `.`
+/// components.
+fn absolute_path(path: &Path) -> PathBuf {
+    let path = match env::current_dir() {
+        Ok(ref dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(config: &Config) -> Config {
+        let scene = SceneFile::parse(&SceneFile::from_config(config).to_toml()).unwrap();
+        let mut loaded = Config::for_mode(scene.mode().unwrap().unwrap());
+        scene.apply(&mut loaded).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn configs_round_trip_through_scene_files() {
+        let mut config = Config::for_mode(Mode::Animated);
+        config.noise.seed = Some(42);
+        config.noise.octaves = 5;
+        config.grid_size = (96, 64);
+        config.mesh.triangulation = Triangulation::Zigzag;
+        config.mesh.normals = NormalMethod::AreaWeighted;
+        config.max_error = Some(0.02);
+        config.model.scale = [2.0, 2.0, 0.5];
+        config.layers = LayerConfig::procedural_defaults();
+        config.asset_dirs = vec![absolute_path(Path::new("textures"))];
+        config.color_ramp = Some(RampPreset::Magma.ramp());
+        config.camera.fov = 70.0;
+        config.shading = Shading::Flat;
+        config.shader_mode = ShaderMode::Slope;
+        config.wireframe = true;
+
+        assert_eq!(round_trip(&config), config);
+    }
+
+    #[test]
+    fn texture_paths_are_written_absolute() {
+        let config = Config::default();
+        let loaded = round_trip(&config);
+        for (layer, original) in loaded.layers.iter().zip(&config.layers) {
+            match (&layer.texture, &original.texture) {
+                (&TextureSource::File(ref path), &TextureSource::File(ref original)) => {
+                    assert!(path.is_absolute(), "{}", path.display());
+                    assert_eq!(*path, absolute_path(original));
+                }
+                (texture, original) => assert_eq!(texture, original),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(SceneFile::parse("colour = \"red\"").is_err());
+        assert!(SceneFile::parse("[grid]\nsizes = [100, 100]").is_err());
+        assert!(SceneFile::parse("[render]\nshadows = true").is_err());
+        assert!(SceneFile::parse("[[layers]]\ntexture = \"a.png\"\nheigth = [0.0, 1.0]").is_err());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert!(SceneFile::parse("[grid]\nsize = \"large\"").is_err());
+        assert!(SceneFile::parse("[noise]\noctaves = -1").is_err());
+
+        let invalid = [
+            "[grid]\ntriangulation = \"diagonal\"",
+            "[grid]\nnormals = \"guessed\"",
+            "[grid]\nshading = \"glossy\"",
+            "[noise]\ninterpolator = \"nearest\"",
+            "[colors]\nramp = \"plaid\"",
+            "[render]\nshader = \"toon\"",
+            "[[layers]]\nheight = [0.0, 1.0]",
+        ];
+        for text in invalid.iter() {
+            let scene = SceneFile::parse(text).unwrap();
+            assert!(scene.apply(&mut Config::default()).is_err(), "{}", text);
+        }
+        let scene = SceneFile::parse("mode = \"sideways\"").unwrap();
+        assert!(scene.mode().is_err());
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_scene_directory() {
+        let dir = env::temp_dir().join(format!("scene-test-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scene.toml");
+        fs::write(
+            &path,
+            "[[layers]]\ntexture = \"grass.jpg\"\n\n[assets]\ndirs = [\"textures\"]\n",
+        ).unwrap();
+
+        let scene = SceneFile::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        let scene = scene.unwrap();
+        let layers = scene.layers.unwrap();
+        assert_eq!(layers[0].texture, Some(dir.join("grass.jpg")));
+        assert_eq!(scene.assets.dirs, Some(vec![dir.join("textures")]));
+    }
+}
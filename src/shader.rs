@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+
+use glium;
+use glium::backend::Facade;
+
+use error::{self, Error};
+
+/// How the terrain is shaded, cycled through with the H key.
+///
+/// The modes after `Gouraud` are unlit views of a single property of the surface,
+/// for debugging meshes and shaders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderMode {
+    /// Textured, with the lighting computed for every pixel.
+    PerPixel,
+    /// Textured, with the lighting computed for every vertex and interpolated.
+    Gouraud,
+    /// Colored by height with the color ramp.
+    Height,
+    /// The world space normals as red, green and blue.
+    Normals,
+    /// The fractional part of the texture coordinates as red and green.
+    TexCoords,
+    /// Colored by slope with the color ramp, from flat to vertical.
+    Slope,
+    /// Distance from the camera, from black at the nearest point of the terrain to
+    /// white at the furthest.
+    Depth,
+}
+
+impl ShaderMode {
+    pub const NAMES: &'static [&'static str] = &[
+        "per-pixel",
+        "gouraud",
+        "height",
+        "normals",
+        "tex-coords",
+        "slope",
+        "depth",
+    ];
+
+    pub const ALL: &'static [ShaderMode] = &[
+        ShaderMode::PerPixel,
+        ShaderMode::Gouraud,
+        ShaderMode::Height,
+        ShaderMode::Normals,
+        ShaderMode::TexCoords,
+        ShaderMode::Slope,
+        ShaderMode::Depth,
+    ];
+
+    /// The mode after this one in `ALL`, going back to the first after the last.
+    pub fn next(&self) -> ShaderMode {
+        let index = ShaderMode::ALL.iter().position(|mode| mode == self).unwrap();
+        ShaderMode::ALL[(index + 1) % ShaderMode::ALL.len()]
+    }
+
+    /// The `debug_view` uniform of `debug_frag.glsl` that shows this mode, or 0 for
+    /// the lit modes.
+    fn debug_view(&self) -> i32 {
+        match *self {
+            ShaderMode::PerPixel | ShaderMode::Gouraud => 0,
+            ShaderMode::Height => 1,
+            ShaderMode::Normals => 2,
+            ShaderMode::TexCoords => 3,
+            ShaderMode::Slope => 4,
+            ShaderMode::Depth => 5,
+        }
+    }
+}
+
+impl Default for ShaderMode {
+    fn default() -> ShaderMode {
+        ShaderMode::PerPixel
+    }
+}
+
+impl FromStr for ShaderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ShaderMode, String> {
+        match s {
+            "per-pixel" => Ok(ShaderMode::PerPixel),
+            "gouraud" => Ok(ShaderMode::Gouraud),
+            "height" => Ok(ShaderMode::Height),
+            "normals" => Ok(ShaderMode::Normals),
+            "tex-coords" => Ok(ShaderMode::TexCoords),
+            "slope" => Ok(ShaderMode::Slope),
+            "depth" => Ok(ShaderMode::Depth),
+            _ => Err(format!("unknown shader mode '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for ShaderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ShaderMode::PerPixel => "per-pixel",
+            ShaderMode::Gouraud => "gouraud",
+            ShaderMode::Height => "height",
+            ShaderMode::Normals => "normals",
+            ShaderMode::TexCoords => "tex-coords",
+            ShaderMode::Slope => "slope",
+            ShaderMode::Depth => "depth",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The programs the terrain can be drawn with.
+///
+/// They are all built up front, so switching modes never waits for shaders to
+/// compile and a shader that doesn't compile is reported at startup.
+pub struct TerrainPrograms {
+    per_pixel: glium::Program,
+    gouraud: glium::Program,
+    /// Shares the vertex shader of `per_pixel`, and shows the property picked by
+    /// its `debug_view` uniform.
+    debug: glium::Program,
+}
+
+impl TerrainPrograms {
+    pub fn new(facade: &Facade) -> error::Result<TerrainPrograms> {
+        let per_pixel = program!(facade,
+            330 => {
+                vertex: include_str!("glsl/lighting_per_pixel_vert.glsl"),
+                fragment: include_str!("glsl/lighting_per_pixel_frag.glsl"),
+        }).map_err(|err| Error::Shader("lighting_per_pixel", err))?;
+        let gouraud = program!(facade,
+            330 => {
+                vertex: include_str!("glsl/lighting_vert.glsl"),
+                fragment: include_str!("glsl/lighting_frag.glsl"),
+        }).map_err(|err| Error::Shader("lighting", err))?;
+        let debug = program!(facade,
+            330 => {
+                vertex: include_str!("glsl/lighting_per_pixel_vert.glsl"),
+                fragment: include_str!("glsl/debug_frag.glsl"),
+        }).map_err(|err| Error::Shader("debug", err))?;
+
+        Ok(TerrainPrograms {
+            per_pixel,
+            gouraud,
+            debug,
+        })
+    }
+
+    /// The program that draws `mode`, and the `debug_view` uniform to draw it with.
+    pub fn get(&self, mode: ShaderMode) -> (&glium::Program, i32) {
+        let program = match mode {
+            ShaderMode::PerPixel => &self.per_pixel,
+            ShaderMode::Gouraud => &self.gouraud,
+            _ => &self.debug,
+        };
+        (program, mode.debug_view())
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use config::{CameraConfig, Config, ConfigOverrides, InterpolatorKind, Mode};
+use grid::{NormalMethod, Normalization, Shading, Triangulation};
+use shader::ShaderMode;
+
+/// Prefix of the strings encoded by this version of the format.
+const VERSION: &'static str = "nv2";
+const SEPARATOR: char = '~';
+const LIST_SEPARATOR: char = '_';
+const NUM_FIELDS: usize = 18;
+
+/// Encodes the view shown with `config` into a short string that only contains
+/// URL-safe characters, such as
+/// `nv2~static~2_2~10~3~improved~1234~200_200~minmax~uniform~face-average~~smooth~per-pixel~0_0_0~1.5707964~1.5707964~45`.
+///
+/// The string holds the mode, noise parameters, seed, grid size, normalization, mesh
+/// options, maximum error, shading, shader mode and camera pose. Materials, lights,
+/// texture layers, the color ramp and the model transform are left out, so they come
+/// from the defaults of the mode or from the scene file when the string is decoded.
+/// A missing seed or maximum error is encoded as an empty field.
+pub fn encode(config: &Config) -> String {
+    let camera = &config.camera;
+    let fields = [
+        VERSION.to_string(),
+        config.mode.to_string(),
+        join(&config.noise.dimensions),
+        config.noise.octaves.to_string(),
+        config.noise.persistence.to_string(),
+        config.noise.interpolator.to_string(),
+        config.noise.seed.map(|s| s.to_string()).unwrap_or_default(),
+        join(&[config.grid_size.0, config.grid_size.1]),
+        config.normalization.to_string(),
+        config.mesh.triangulation.to_string(),
+        config.mesh.normals.to_string(),
+        config.max_error.map(|e| e.to_string()).unwrap_or_default(),
+        config.shading.to_string(),
+        config.shader_mode.to_string(),
+        join(&camera.position),
+        camera.theta.to_string(),
+        camera.phi.to_string(),
+        camera.fov.to_string(),
+    ];
+
+    let mut s = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            s.push(SEPARATOR);
+        }
+        s.push_str(field);
+    }
+    s
+}
+
+/// Decodes a string made by `encode` into the values it sets.
+pub fn decode(s: &str) -> Result<ConfigOverrides, String> {
+    let fields: Vec<&str> = s.trim().split(SEPARATOR).collect();
+    if fields[0] != VERSION {
+        return Err(format!(
+            "share strings must start with '{}{}'",
+            VERSION, SEPARATOR
+        ));
+    }
+    if fields.len() != NUM_FIELDS {
+        return Err(format!(
+            "share strings have {} fields, got {}",
+            NUM_FIELDS,
+            fields.len()
+        ));
+    }
+
+    let mode = Mode::from_str(fields[1])?;
+    let dimensions: Vec<u32> = split("dimensions", fields[2])?;
+    if dimensions.len() != mode.noise_dimensions() {
+        return Err(format!(
+            "{} mode needs {} noise dimensions, got {}",
+            mode,
+            mode.noise_dimensions(),
+            dimensions.len()
+        ));
+    }
+    let seed = match fields[6] {
+        "" => None,
+        seed => Some(parse_field("seed", seed)?),
+    };
+    let grid_size: Vec<u32> = split("grid size", fields[7])?;
+    if grid_size.len() != 2 {
+        return Err(format!("invalid grid size '{}'", fields[7]));
+    }
+    let max_error = match fields[11] {
+        "" => None,
+        max_error => Some(parse_field("maximum error", max_error)?),
+    };
+    let position: Vec<f32> = split("camera position", fields[14])?;
+    if position.len() != 3 {
+        return Err(format!("invalid camera position '{}'", fields[14]));
+    }
+
+    Ok(ConfigOverrides {
+        mode: Some(mode),
+        dimensions: Some(dimensions),
+        octaves: Some(parse_field("octaves", fields[3])?),
+        persistence: Some(parse_field("persistence", fields[4])?),
+        interpolator: Some(InterpolatorKind::from_str(fields[5])?),
+        grid_size: Some((grid_size[0], grid_size[1])),
+        normalization: Some(Normalization::from_str(fields[8])?),
+        triangulation: Some(Triangulation::from_str(fields[9])?),
+        normals: Some(NormalMethod::from_str(fields[10])?),
+        max_error,
+        seed,
+        layers: None,
+        asset_dirs: Vec::new(),
+        color_ramp: None,
+        camera: Some(CameraConfig {
+            position: [position[0], position[1], position[2]],
+            theta: parse_field("camera theta", fields[15])?,
+            phi: parse_field("camera phi", fields[16])?,
+            fov: parse_field("camera fov", fields[17])?,
+        }),
+        shading: Some(Shading::from_str(fields[12])?),
+        shader_mode: Some(ShaderMode::from_str(fields[13])?),
+        window_size: None,
+    })
+}
+
+fn join<T: Display>(values: &[T]) -> String {
+    let strings: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    strings.join(&LIST_SEPARATOR.to_string())
+}
+
+fn split<T: FromStr>(name: &str, s: &str) -> Result<Vec<T>, String> {
+    s.split(LIST_SEPARATOR)
+        .map(|value| parse_field(name, value))
+        .collect()
+}
+
+fn parse_field<T: FromStr>(name: &str, s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid {} '{}'", name, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::MeshOptions;
+
+    fn decode_into(s: &str, mode: Mode) -> Config {
+        let mut config = Config::for_mode(mode);
+        decode(s).unwrap().apply(&mut config);
+        config
+    }
+
+    #[test]
+    fn decoding_an_encoded_config_restores_it() {
+        let mut config = Config::for_mode(Mode::Animated);
+        config.noise.seed = Some(1234);
+        config.noise.persistence = 2.75;
+        config.grid_size = (64, 48);
+        config.camera.position = [0.5, -1.25, 3.0];
+        config.camera.theta = 0.1;
+        config.camera.fov = 60.0;
+        config.mesh = MeshOptions {
+            triangulation: Triangulation::SlopeAware,
+            normals: NormalMethod::Sobel,
+        };
+        config.max_error = Some(0.015);
+        config.shading = Shading::Flat;
+        config.shader_mode = ShaderMode::Normals;
+
+        assert_eq!(decode_into(&encode(&config), Mode::Animated), config);
+    }
+
+    #[test]
+    fn dimensions_must_match_the_mode() {
+        let s = encode(&Config::for_mode(Mode::Static));
+        assert!(decode(&s.replace("~2_2~", "~2_2_3~")).is_err());
+
+        let s = encode(&Config::for_mode(Mode::Animated));
+        assert!(decode(&s.replace("~2_2_3~", "~2_2~")).is_err());
+    }
+
+    #[test]
+    fn missing_seed_round_trips() {
+        let config = Config::for_mode(Mode::Static);
+        assert_eq!(config.noise.seed, None);
+
+        let s = encode(&config);
+        assert_eq!(decode_into(&s, Mode::Static), config);
+        assert_eq!(decode(&s).unwrap().seed, None);
+    }
+
+    #[test]
+    fn example_string_decodes() {
+        let s =
+            "nv2~static~2_2~10~3~improved~1234~200_200~minmax~uniform~face-average~~smooth~per-pixel~0_0_0~1.5707964~1.5707964~45";
+        let config = decode_into(s, Mode::Static);
+        assert_eq!(config.noise.seed, Some(1234));
+        assert_eq!(config.grid_size, (200, 200));
+        assert_eq!(encode(&config), s);
+    }
+
+    #[test]
+    fn invalid_strings_are_rejected() {
+        assert!(decode("").is_err());
+        assert!(decode("nv9~static").is_err());
+        assert!(decode("nv2~static~2_2~10").is_err());
+
+        // Strings of the first version lack the mesh options and shader mode.
+        let old = "nv1~static~2_2~10~3~improved~~200_200~minmax~smooth~0_0_0~1.5~1.5~45";
+        assert!(decode(old).is_err());
+
+        let s = encode(&Config::default());
+        assert!(decode(&s.replace("static", "sideways")).is_err());
+        assert!(decode(&s.replace("200_200", "200")).is_err());
+        assert!(decode(&format!("{}~1", s)).is_err());
+    }
+}
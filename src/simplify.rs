@@ -0,0 +1,242 @@
+use std::f64;
+
+use grid::{Grid, Index, Vertex};
+
+type Point = (u32, u32);
+
+/// Builds a reduced triangle mesh of `grid` whose surface deviates from the grid
+/// values by at most `max_error` vertically.
+///
+/// Uses a right-triangulated irregular network (RTIN): the grid is covered by two
+/// right triangles that are recursively split along their hypotenuse wherever the
+/// error exceeds the tolerance. Grids that aren't `2^k + 1` points square are
+/// treated as part of the next such square; triangles crossing the grid border are
+/// always split, and those outside of it are dropped.
+///
+/// Vertices are laid out like `Grid::gen_vertex_buffer`, with normals from
+/// `Grid::normal_at`, so the result can be used in place of the full mesh.
+pub fn simplify_grid(grid: &Grid, max_error: f64) -> (Vec<Vertex>, Vec<Index>) {
+    assert!(max_error >= 0.0);
+
+    let rtin = Rtin::new(grid);
+    let mut mesh = MeshBuilder {
+        grid,
+        vertex_map: vec![Index::max_value(); (grid.width() * grid.height()) as usize],
+        vertices: Vec::new(),
+        indices: Vec::new(),
+    };
+
+    let max = rtin.size - 1;
+    rtin.emit(&mut mesh, (0, 0), (max, max), (max, 0), max_error);
+    rtin.emit(&mut mesh, (max, max), (0, 0), (0, max), max_error);
+
+    (mesh.vertices, mesh.indices)
+}
+
+struct Rtin<'a> {
+    grid: &'a Grid,
+    size: u32,
+    errors: Vec<f64>,
+}
+
+enum Coverage {
+    Inside,
+    Outside,
+    Border,
+}
+
+impl<'a> Rtin<'a> {
+    fn new(grid: &'a Grid) -> Rtin<'a> {
+        let tiles = (grid.width().max(grid.height()) - 1).next_power_of_two();
+        let size = tiles + 1;
+
+        let mut rtin = Rtin {
+            grid,
+            size,
+            errors: vec![0.0; (size * size) as usize],
+        };
+        rtin.compute_errors();
+        rtin
+    }
+
+    /// Fills in the error at the hypotenuse midpoint of every triangle.
+    ///
+    /// Levels are processed from the smallest triangles up, so that each midpoint
+    /// error already includes both triangles sharing it before a parent reads it.
+    /// That makes a triangle split whenever its neighbour does, which keeps the
+    /// final mesh free of T-junctions.
+    fn compute_errors(&mut self) {
+        let max = self.size - 1;
+        let roots = [
+            ((0, 0), (max, max), (max, 0)),
+            ((max, max), (0, 0), (0, max)),
+        ];
+
+        for depth in (0..(self.max_depth() + 1)).rev() {
+            for &(a, b, c) in &roots {
+                self.visit_level(a, b, c, depth);
+            }
+        }
+    }
+
+    /// The depth of the smallest triangles below the two root triangles.
+    fn max_depth(&self) -> u32 {
+        let max = self.size - 1;
+        let (mut a, mut b, mut c) = ((0, 0), (max, max), (max, 0));
+        let mut depth = 0;
+
+        while leg_length(a, c) > 1 {
+            let m = midpoint(a, b);
+            b = a;
+            a = c;
+            c = m;
+            depth += 1;
+        }
+        depth
+    }
+
+    fn visit_level(&mut self, a: Point, b: Point, c: Point, depth: u32) {
+        // The hypotenuse of the smallest triangles is a cell diagonal, whose midpoint
+        // isn't a grid point, so they have no error of their own.
+        if leg_length(a, c) == 1 {
+            return;
+        }
+        let m = midpoint(a, b);
+
+        if depth > 0 {
+            self.visit_level(c, a, m, depth - 1);
+            self.visit_level(b, c, m, depth - 1);
+            return;
+        }
+
+        let mut error = match self.coverage(a, b, c) {
+            Coverage::Inside => {
+                let interpolated = (self.height(a) + self.height(b)) / 2.0;
+                (interpolated - self.height(m)).abs()
+            }
+            Coverage::Outside => 0.0,
+            Coverage::Border => f64::INFINITY,
+        };
+
+        if leg_length(c, m) > 1 {
+            error = error
+                .max(self.error_at(midpoint(c, a)))
+                .max(self.error_at(midpoint(b, c)));
+        }
+
+        let i = self.index(m);
+        self.errors[i] = self.errors[i].max(error);
+    }
+
+    fn emit(&self, mesh: &mut MeshBuilder, a: Point, b: Point, c: Point, max_error: f64) {
+        let m = midpoint(a, b);
+        let error = self.error_at(m);
+
+        if leg_length(a, c) > 1 && (error > max_error || error == f64::INFINITY) {
+            self.emit(mesh, c, a, m, max_error);
+            self.emit(mesh, b, c, m, max_error);
+        } else if let Coverage::Inside = self.coverage(a, b, c) {
+            mesh.push_triangle(a, b, c);
+        }
+    }
+
+    fn coverage(&self, a: Point, b: Point, c: Point) -> Coverage {
+        let (last_x, last_y) = (self.grid.width() - 1, self.grid.height() - 1);
+        let inside = |p: Point| p.0 <= last_x && p.1 <= last_y;
+
+        if inside(a) && inside(b) && inside(c) {
+            Coverage::Inside
+        } else if a.0.min(b.0).min(c.0) >= last_x || a.1.min(b.1).min(c.1) >= last_y {
+            Coverage::Outside
+        } else {
+            Coverage::Border
+        }
+    }
+
+    fn height(&self, p: Point) -> f64 {
+        self.grid.value(p.0, p.1)
+    }
+    fn error_at(&self, p: Point) -> f64 {
+        self.errors[self.index(p)]
+    }
+    fn index(&self, p: Point) -> usize {
+        (p.0 + p.1 * self.size) as usize
+    }
+}
+
+struct MeshBuilder<'a> {
+    grid: &'a Grid,
+    vertex_map: Vec<Index>,
+    vertices: Vec<Vertex>,
+    indices: Vec<Index>,
+}
+
+impl<'a> MeshBuilder<'a> {
+    fn push_triangle(&mut self, a: Point, b: Point, c: Point) {
+        for &p in &[a, b, c] {
+            let index = self.vertex_index(p);
+            self.indices.push(index);
+        }
+    }
+
+    fn vertex_index(&mut self, p: Point) -> Index {
+        let grid_index = (p.0 + p.1 * self.grid.width()) as usize;
+
+        if self.vertex_map[grid_index] == Index::max_value() {
+            self.vertex_map[grid_index] = self.vertices.len() as Index;
+            self.vertices.push(self.grid.vertex_at(p.0, p.1));
+        }
+        self.vertex_map[grid_index]
+    }
+}
+
+fn midpoint(a: Point, b: Point) -> Point {
+    ((a.0 + b.0) / 2, (a.1 + b.1) / 2)
+}
+
+fn leg_length(a: Point, c: Point) -> u32 {
+    let dx = (i64::from(a.0) - i64::from(c.0)).abs();
+    let dy = (i64::from(a.1) - i64::from(c.1)).abs();
+    (dx + dy) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_from_fn<F: Fn(u32, u32) -> f64>(width: u32, height: u32, f: F) -> Grid {
+        let mut values = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                values.push(f(x, y));
+            }
+        }
+        Grid::from_vec(values, width, height)
+    }
+
+    fn plane(x: u32, y: u32) -> f64 {
+        0.25 + 0.02 * f64::from(x) + 0.01 * f64::from(y)
+    }
+
+    #[test]
+    fn planar_grid_simplifies_to_two_triangles() {
+        let (vertices, indices) = simplify_grid(&grid_from_fn(17, 17, plane), 1e-9);
+        assert_eq!(indices.len(), 6);
+        assert_eq!(vertices.len(), 4);
+    }
+
+    #[test]
+    fn planar_grid_of_any_size_stays_coarse() {
+        // Triangles crossing the border are split, but not all the way down.
+        let (_, indices) = simplify_grid(&grid_from_fn(12, 7, plane), 1e-9);
+        assert!(indices.len() / 3 < 11 * 6 * 2);
+    }
+
+    #[test]
+    fn zero_error_keeps_every_point_of_a_bumpy_grid() {
+        let grid = grid_from_fn(5, 5, |x, y| if (x + y) % 2 == 0 { 1.0 } else { 0.0 });
+        let (vertices, indices) = simplify_grid(&grid, 0.0);
+        assert_eq!(vertices.len(), 25);
+        assert_eq!(indices.len(), 4 * 4 * 2 * 3);
+    }
+}
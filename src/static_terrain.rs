@@ -0,0 +1,90 @@
+use cgmath::Matrix4;
+
+use error;
+use geom::{Geometry, GeometryProvider};
+use grid::{self, Grid, MeshOptions, Shading};
+use simplify;
+use visualizer::{Index, Vertex, Visualizer};
+
+/// A single mesh of a grid, optionally simplified.
+///
+/// The grid is kept around so the mesh can be rebuilt when the visualizer switches
+/// between smooth and flat shading, without sampling the noise again.
+pub struct StaticTerrain {
+    grid: Grid,
+    model: Matrix4<f32>,
+    mesh_options: MeshOptions,
+    max_error: Option<f64>,
+    shading: Option<Shading>,
+    geometry: Option<Geometry<Vertex, Index>>,
+}
+
+impl StaticTerrain {
+    pub fn new(grid: Grid, model: Matrix4<f32>) -> StaticTerrain {
+        StaticTerrain {
+            grid,
+            model,
+            mesh_options: MeshOptions::default(),
+            max_error: None,
+            shading: None,
+            geometry: None,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+    pub fn model(&self) -> &Matrix4<f32> {
+        &self.model
+    }
+
+    pub fn set_mesh_options(&mut self, options: MeshOptions) -> &mut StaticTerrain {
+        self.mesh_options = options;
+        self.geometry = None;
+        self
+    }
+    /// Simplifies the mesh with `simplify::simplify_grid` using the given maximum
+    /// vertical error, or disables simplification with `None`.
+    pub fn set_max_error(&mut self, max_error: Option<f64>) -> &mut StaticTerrain {
+        self.max_error = max_error;
+        self.geometry = None;
+        self
+    }
+
+    fn build_geometry(&mut self, vis: &Visualizer, shading: Shading) -> error::Result<()> {
+        let (vertices, indices) = match self.max_error {
+            Some(max_error) => simplify::simplify_grid(&self.grid, max_error),
+            None => self.grid.gen_vertex_buffer_with(&self.mesh_options),
+        };
+        let (vertices, indices) = match shading {
+            Shading::Smooth => (vertices, indices),
+            Shading::Flat => grid::flatten_mesh(&vertices, &indices),
+        };
+
+        self.geometry = Some(Geometry::from_mesh(
+            vis.display(),
+            &vertices,
+            &indices,
+            self.model,
+        )?);
+        self.shading = Some(shading);
+        Ok(())
+    }
+}
+
+impl GeometryProvider<Vertex, Index> for StaticTerrain {
+    fn geometries(&self) -> Vec<&Geometry<Vertex, Index>> {
+        self.geometry.iter().collect()
+    }
+
+    fn raw_range(&self) -> Option<(f64, f64)> {
+        Some(self.grid.raw_range())
+    }
+
+    fn update(&mut self, vis: &Visualizer) -> error::Result<()> {
+        if self.geometry.is_none() || self.shading != Some(vis.shading()) {
+            self.build_geometry(vis, vis.shading())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::f64;
+
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+use noise_lib;
+
+use error::{self, Error};
+use geom::{Geometry, GeometryProvider};
+use grid::{self, Shading};
+use visualizer::{Index, Vertex, Visualizer};
+
+/// Cells along each side of a chunk in streaming mode.
+pub const DEFAULT_CHUNK_CELLS: u32 = 32;
+/// Chunks kept loaded around the camera in streaming mode, in chunk widths.
+pub const DEFAULT_VIEW_DISTANCE: u32 = 6;
+
+/// Generates terrain chunks on demand around the camera from an unbounded noise
+/// function.
+///
+/// Chunks are addressed by integer coordinates on the xy plane and cover
+/// `chunk_cells` x `chunk_cells` cells. Every cell corner is sampled at its absolute
+/// lattice position, so neighbouring chunks agree exactly along their borders.
+/// Chunks beyond the view distance are evicted from the cache.
+pub struct StreamingTerrain<N>
+where
+    N: noise_lib::noise::Noise<IndexType = Vector2<f64>>,
+{
+    noise: N,
+    model: Matrix4<f32>,
+    chunk_cells: u32,
+    cell_spacing: f64,
+    value_range: (f64, f64),
+    view_distance: u32,
+    chunks_per_frame: usize,
+    shading: Shading,
+    is_loading: bool,
+    chunks: HashMap<(i32, i32), Geometry<Vertex, Index>>,
+}
+
+impl<N> StreamingTerrain<N>
+where
+    N: noise_lib::noise::Noise<IndexType = Vector2<f64>>,
+{
+    /// Creates a streaming terrain where each cell spans `cell_spacing` noise units
+    /// and `model` maps cell coordinates into the world.
+    ///
+    /// Chunks within `view_distance` chunks of the camera are kept loaded.
+    pub fn new(
+        noise: N,
+        model: Matrix4<f32>,
+        chunk_cells: u32,
+        cell_spacing: f64,
+        view_distance: u32,
+    ) -> StreamingTerrain<N> {
+        assert!(chunk_cells > 0);
+
+        let mut terrain = StreamingTerrain {
+            noise,
+            model,
+            chunk_cells,
+            cell_spacing,
+            value_range: (0.0, 1.0),
+            view_distance,
+            chunks_per_frame: 4,
+            shading: Shading::Smooth,
+            is_loading: true,
+            chunks: HashMap::new(),
+        };
+        terrain.calibrate(chunk_cells * 4);
+        terrain
+    }
+
+    /// Estimates the range of noise values from a `size` x `size` patch around the
+    /// origin, which is then used to map all samples into `[0, 1]`.
+    ///
+    /// A normalization per chunk would make borders disagree, so a single range is
+    /// shared by every chunk; values outside of it are clamped. Clears the chunk cache.
+    pub fn calibrate(&mut self, size: u32) {
+        let half = i64::from(size / 2);
+        let patch = grid::sample_noise_grid(
+            &self.noise,
+            (-half, -half),
+            self.cell_spacing,
+            (size.max(2), size.max(2)),
+        );
+
+        let (mut min, mut max) = (f64::MAX, f64::MIN);
+        for &value in patch.as_ref() {
+            min = min.min(value);
+            max = max.max(value);
+        }
+
+        self.value_range = (min, max);
+        self.chunks.clear();
+    }
+
+    pub fn set_value_range(&mut self, range: (f64, f64)) -> &mut StreamingTerrain<N> {
+        self.value_range = range;
+        self.chunks.clear();
+        self
+    }
+    /// Sets the maximum number of chunks generated in a single frame.
+    pub fn set_chunks_per_frame(&mut self, count: usize) -> &mut StreamingTerrain<N> {
+        self.chunks_per_frame = count.max(1);
+        self
+    }
+
+    pub fn value_range(&self) -> (f64, f64) {
+        self.value_range
+    }
+    pub fn num_loaded_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    fn build_chunk(
+        &self,
+        vis: &Visualizer,
+        key: (i32, i32),
+    ) -> error::Result<Geometry<Vertex, Index>> {
+        let n = self.chunk_cells;
+        let origin = (
+            i64::from(key.0) * i64::from(n) - 1,
+            i64::from(key.1) * i64::from(n) - 1,
+        );
+
+        // Sample one extra point past each border so normals along the edges are
+        // computed from the same values in both neighbouring chunks.
+        let mut chunk_grid =
+            grid::sample_noise_grid(&self.noise, origin, self.cell_spacing, (n + 3, n + 3));
+        let (min, max) = self.value_range;
+        let coeff = if max > min { 1.0 / (max - min) } else { 0.0 };
+        for value in chunk_grid.as_mut() {
+            *value = ((*value - min) * coeff).max(0.0).min(1.0);
+        }
+
+        let (vertices, indices) = chunk_grid.gen_region_vertex_buffer((1, 1), (n, n), 1, 0.0);
+        let (vertices, indices) = match self.shading {
+            Shading::Smooth => (vertices, indices),
+            Shading::Flat => grid::flatten_mesh(&vertices, &indices),
+        };
+        let vertices: Vec<Vertex> = vertices
+            .iter()
+            .map(|v| {
+                let pos = v.position();
+                let tex_coord = [
+                    (origin.0 as f32 + pos[0]) / n as f32,
+                    (origin.1 as f32 + pos[1]) / n as f32,
+                ];
+                Vertex::new(pos, v.normal(), tex_coord)
+            })
+            .collect();
+
+        let model = self.model
+            * Matrix4::from_translation(Vector3::new(origin.0 as f32, origin.1 as f32, 0.0));
+
+        Geometry::from_mesh(vis.display(), &vertices, &indices, model)
+    }
+
+    /// The position of the camera in chunk coordinates.
+    fn camera_chunk_position(&self, vis: &Visualizer) -> error::Result<(f32, f32)> {
+        let camera = vis.camera_position();
+        let to_cells = self.model.invert().ok_or_else(|| {
+            Error::Invalid("the model matrix has a zero scale, so it can't be inverted".to_string())
+        })?;
+        let pos = to_cells * Vector4::new(camera.x, camera.y, camera.z, 1.0);
+
+        Ok((
+            pos.x / self.chunk_cells as f32,
+            pos.y / self.chunk_cells as f32,
+        ))
+    }
+}
+
+impl<N> GeometryProvider<Vertex, Index> for StreamingTerrain<N>
+where
+    N: noise_lib::noise::Noise<IndexType = Vector2<f64>>,
+{
+    fn geometries(&self) -> Vec<&Geometry<Vertex, Index>> {
+        self.chunks.values().collect()
+    }
+
+    fn is_loading(&self) -> bool {
+        self.is_loading
+    }
+
+    fn raw_range(&self) -> Option<(f64, f64)> {
+        Some(self.value_range)
+    }
+
+    fn update(&mut self, vis: &Visualizer) -> error::Result<()> {
+        if self.shading != vis.shading() {
+            self.shading = vis.shading();
+            self.chunks.clear();
+        }
+
+        let (cx, cy) = self.camera_chunk_position(vis)?;
+        let radius = self.view_distance as f32;
+        let chunk_distance = |key: &(i32, i32)| {
+            let (dx, dy) = (key.0 as f32 + 0.5 - cx, key.1 as f32 + 0.5 - cy);
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        // Keep a margin of one chunk before evicting, so chunks on the boundary
+        // are not rebuilt every time the camera moves back and forth.
+        self.chunks
+            .retain(|key, _| chunk_distance(key) <= radius + 1.0);
+
+        let r = self.view_distance as i32 + 1;
+        let (center_x, center_y) = (cx.floor() as i32, cy.floor() as i32);
+        let mut missing = Vec::new();
+        for y in (center_y - r)..(center_y + r + 1) {
+            for x in (center_x - r)..(center_x + r + 1) {
+                let key = (x, y);
+                if !self.chunks.contains_key(&key) && chunk_distance(&key) <= radius {
+                    missing.push(key);
+                }
+            }
+        }
+
+        missing.sort_by(|a, b| {
+            chunk_distance(a)
+                .partial_cmp(&chunk_distance(b))
+                .unwrap()
+        });
+
+        self.is_loading = missing.len() > self.chunks_per_frame;
+        for key in missing.into_iter().take(self.chunks_per_frame) {
+            let chunk = self.build_chunk(vis, key)?;
+            self.chunks.insert(key, chunk);
+        }
+        Ok(())
+    }
+}
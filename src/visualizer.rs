@@ -2,19 +2,35 @@ use std::ops::Deref;
 use std::f32;
 use std::cell::RefCell;
 use std::fs;
-use std::io;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use glium::uniforms;
 use glium::glutin;
 use glium::{self, Surface};
+use glium::backend::Facade;
 use cgmath::{self, InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
 use image;
 
+use assets::{self, AssetLoader};
+use builder;
 use camera_controller::CameraController;
-use geom;
+use color_ramp::{ColorRamp, RampPreset};
+use config::{CameraConfig, Config, LayerConfig, MaterialConfig, TextureSource};
+use error::{self, Error};
+use geom::{self, Bounds};
+use grid::Shading;
+use legend;
+use map_view::MapView;
+use scene::{SceneFile, SceneWatcher};
+use shader::{ShaderMode, TerrainPrograms};
+use share;
 
-const MAX_MATERIALS: usize = 5;
+pub const MAX_MATERIALS: usize = 5;
+pub const MAX_LAYERS: usize = 16;
+/// Number of texels color ramps are sampled into.
+const RAMP_RESOLUTION: usize = 256;
 
 #[derive(Copy, Clone)]
 struct Lights {
@@ -39,31 +55,133 @@ pub struct Materials {
 }
 implement_uniform_block!(Materials, mat);
 
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Layer {
+    pub height: [f32; 2],
+    pub slope: [f32; 2],
+    pub scale: f32,
+    pub blend: f32,
+    pub _padding: [f32; 2],
+}
+implement_uniform_block!(Layer, height, slope, scale, blend);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Layers {
+    pub layer: [Layer; MAX_LAYERS],
+}
+implement_uniform_block!(Layers, layer);
+
 pub type Index = u32;
 pub use grid::Vertex;
 
+/// A corner of a quad drawn over the view, in normalized device coordinates.
+#[derive(Copy, Clone)]
+struct OverlayVertex {
+    position: [f32; 2],
+    tex_coord: [f32; 2],
+}
+implement_vertex!(OverlayVertex, position, tex_coord);
+
+/// Gap between the legend and the edges of the view, in pixels.
+const LEGEND_MARGIN: u32 = 10;
+
+/// Where the visualizer draws to.
+enum Backend {
+    /// An interactive window.
+    Window {
+        events_loop: glutin::EventsLoop,
+        display: glium::Display,
+    },
+    /// An offscreen context, only drawn to with `Visualizer::render_image`.
+    Headless(glium::HeadlessRenderer),
+}
+
 pub struct Visualizer {
-    events_loop: glutin::EventsLoop,
-    display: glium::Display,
+    backend: Backend,
     running: bool,
-    shader_program: glium::Program,
+    time: f64,
+    terrain_programs: TerrainPrograms,
+    /// How the terrain is shaded, cycled through with the H key.
+    shader_mode: ShaderMode,
+    map_program: glium::Program,
+    overlay_program: glium::Program,
     camera_controller: RefCell<CameraController>,
     geometry: Option<RefCell<Box<geom::GeometryProvider<Vertex, Index>>>>,
     is_wireframe: bool,
     is_focused: bool,
+    shading: Shading,
+    /// Whether the terrain is shown as a flat map instead of from the camera.
+    show_map: bool,
+    /// Fitted to the terrain the first time the map is shown.
+    map_view: Option<MapView>,
+    cursor_position: (f64, f64),
+    is_panning: bool,
     materials: Option<glium::uniforms::UniformBuffer<Materials>>,
+    lights: Lights,
 
-    textures: Vec<glium::texture::Texture2d>,
+    layers: Vec<LayerConfig>,
+    assets: AssetLoader,
+    layer_uniforms: uniforms::UniformBuffer<Layers>,
+    /// The texture of each layer, once loaded.
+    layer_textures: Option<glium::texture::Texture2dArray>,
+    /// Whether layer textures are projected along all three axes, toggled with Y.
+    use_triplanar: bool,
+    /// Whether layers are limited to their slopes, toggled with K.
+    use_slope_layers: bool,
+    /// The ramps cycled through with the G key.
+    color_ramps: Vec<ColorRamp>,
+    /// The index of the ramp coloring the terrain, or `None` to use textures.
+    color_ramp: Option<usize>,
+    /// The active ramp, or the spectrum for the map when the terrain is textured.
+    ramp_texture: glium::texture::Texture1d,
+    next_ramp_requested: bool,
+    /// Whether the colorbar is shown when the terrain is colored by a ramp.
+    show_legend: bool,
+    /// The colorbar image, with the ramp and labels it was drawn with.
+    legend: Option<((Option<usize>, Vec<(f32, String)>), glium::texture::Texture2d)>,
+    config: Option<Config>,
+    pending_seed: Option<u64>,
+    share_requested: bool,
+    screenshot_requested: bool,
+    scene_watcher: Option<SceneWatcher>,
     update_method: Option<Box<FnMut()>>,
 }
 
 impl Visualizer {
-    pub fn new(window_builder: glutin::WindowBuilder) -> Visualizer {
+    pub fn new(window_builder: glutin::WindowBuilder) -> error::Result<Visualizer> {
         let events_loop = glutin::EventsLoop::new();
         let context = glutin::ContextBuilder::new()
             .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3)))
             .with_vsync(false)
             .with_gl_profile(glutin::GlProfile::Core);
+        let display = glium::Display::new(window_builder, context, &events_loop)
+            .map_err(Error::context)?;
+
+        Visualizer::with_backend(Backend::Window {
+            events_loop,
+            display,
+        })
+    }
+
+    /// Creates a visualizer without a window, which can only render images with
+    /// `render_image`.
+    ///
+    /// On Linux the context comes from OSMesa, so this works without a display or a
+    /// GPU when Mesa's software rasterizer is installed.
+    pub fn new_headless() -> error::Result<Visualizer> {
+        // The size of the default framebuffer doesn't matter, since images are
+        // rendered into framebuffers of their own.
+        let context = glutin::HeadlessRendererBuilder::new(1, 1)
+            .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3)))
+            .with_gl_profile(glutin::GlProfile::Core)
+            .build()
+            .map_err(Error::context)?;
+        let renderer = glium::HeadlessRenderer::new(context).map_err(Error::context)?;
+
+        Visualizer::with_backend(Backend::Headless(renderer))
+    }
+
+    fn with_backend(backend: Backend) -> error::Result<Visualizer> {
         let camera_controller = CameraController::new(
             Vector3::new(0.0, 0.0, 0.0),
             Vector3::new(0.0, 1.0, 0.0),
@@ -72,35 +190,79 @@ impl Visualizer {
             f32::consts::PI / 2.0,
         );
 
-        let display = glium::Display::new(window_builder, context, &events_loop).unwrap();
-
-        let shader_program = program!(&display,
+        let terrain_programs = TerrainPrograms::new(backend.facade())?;
+        let map_program = program!(backend.facade(),
+            330 => {
+                vertex: include_str!("glsl/vertex.glsl"),
+                fragment: include_str!("glsl/fragment.glsl"),
+        }).map_err(|err| Error::Shader("map", err))?;
+        let overlay_program = program!(backend.facade(),
             330 => {
-                vertex: include_str!("glsl/lighting_per_pixel_vert.glsl"),
-                fragment: include_str!("glsl/lighting_per_pixel_frag.glsl"),
-        }).unwrap();
+                vertex: include_str!("glsl/overlay_vert.glsl"),
+                fragment: include_str!("glsl/overlay_frag.glsl"),
+        }).map_err(|err| Error::Shader("overlay", err))?;
 
-        Visualizer {
-            events_loop,
-            display: display,
+        let layers = LayerConfig::defaults();
+        let layer_uniforms = build_layer_uniforms(backend.facade(), &layers)?;
+
+        let color_ramps: Vec<_> = RampPreset::ALL.iter().map(|preset| preset.ramp()).collect();
+        let ramp_texture =
+            build_ramp_texture(backend.facade(), &RampPreset::Spectrum.ramp())?;
+
+        Ok(Visualizer {
+            backend,
             running: true,
-            shader_program,
+            time: 0.0,
+            terrain_programs,
+            shader_mode: ShaderMode::default(),
+            map_program,
+            overlay_program,
             camera_controller: RefCell::new(camera_controller),
             geometry: None,
             is_wireframe: false,
             is_focused: true,
+            shading: Shading::Smooth,
+            show_map: false,
+            map_view: None,
+            cursor_position: (0.0, 0.0),
+            is_panning: false,
             materials: None,
+            lights: Lights {
+                light_pos: cgmath::conv::array3(Vector3::new(100.0, -0.0, -500.0_f32).normalize()),
+                light_color: cgmath::conv::array4(Vector4::new(1.0, 1.0, 1.0, 1.0)),
+            },
 
-            textures: Vec::new(),
+            layers,
+            assets: AssetLoader::new(Vec::new()),
+            layer_uniforms,
+            layer_textures: None,
+            use_triplanar: true,
+            use_slope_layers: true,
+            color_ramps,
+            color_ramp: None,
+            ramp_texture,
+            next_ramp_requested: false,
+            show_legend: true,
+            legend: None,
+            config: None,
+            pending_seed: None,
+            share_requested: false,
+            screenshot_requested: false,
+            scene_watcher: None,
             update_method: None,
-        }
+        })
     }
 
-    pub fn display(&self) -> &glium::Display {
-        &self.display
+    /// The context geometry providers create their buffers with.
+    pub fn display(&self) -> &Facade {
+        self.backend.facade()
     }
-    pub fn events_loop(&self) -> &glutin::EventsLoop {
-        &self.events_loop
+    /// The window, unless the visualizer is headless.
+    pub fn window_display(&self) -> Option<&glium::Display> {
+        match self.backend {
+            Backend::Window { ref display, .. } => Some(display),
+            Backend::Headless(_) => None,
+        }
     }
 
     pub fn set_geometry(&mut self, geom: Box<geom::GeometryProvider<Vertex, Index>>) {
@@ -109,33 +271,426 @@ impl Visualizer {
     pub fn set_materials(&mut self, materials: uniforms::UniformBuffer<Materials>) {
         self.materials = Some(materials);
     }
+    pub fn set_light(&mut self, direction: Vector3<f32>, color: Vector4<f32>) {
+        self.lights = Lights {
+            light_pos: cgmath::conv::array3(direction.normalize()),
+            light_color: cgmath::conv::array4(color),
+        };
+    }
+    /// Sets the texture layers painted over the terrain, whose images are loaded when
+    /// `run` starts or right away if it already has.
+    pub fn set_layers(&mut self, layers: Vec<LayerConfig>) -> error::Result<()> {
+        assert!(!layers.is_empty() && layers.len() <= MAX_LAYERS);
+        if layers == self.layers {
+            return Ok(());
+        }
+
+        let paths_changed = layers.len() != self.layers.len()
+            || layers
+                .iter()
+                .zip(&self.layers)
+                .any(|(a, b)| a.texture != b.texture);
+        self.layer_uniforms = build_layer_uniforms(self.display(), &layers)?;
+        self.layers = layers;
+        if paths_changed && self.layer_textures.is_some() {
+            self.load_textures()?;
+        }
+        Ok(())
+    }
+    /// Sets the directories searched for textures that aren't at their own path.
+    pub fn set_asset_dirs(&mut self, dirs: Vec<PathBuf>) -> error::Result<()> {
+        let assets = AssetLoader::new(dirs);
+        if assets == self.assets {
+            return Ok(());
+        }
+
+        self.assets = assets;
+        if self.layer_textures.is_some() {
+            self.load_textures()?;
+        }
+        Ok(())
+    }
+    /// Colors the terrain by height with `ramp`, or with its textures for `None`.
+    ///
+    /// Ramps that aren't presets are added to the ones cycled through with the G key.
+    pub fn set_color_ramp(&mut self, ramp: Option<ColorRamp>) -> error::Result<()> {
+        self.color_ramp = match ramp {
+            Some(ramp) => match self.color_ramps.iter().position(|r| *r == ramp) {
+                Some(index) => Some(index),
+                None => {
+                    self.color_ramps.push(ramp);
+                    Some(self.color_ramps.len() - 1)
+                }
+            },
+            None => None,
+        };
+        self.update_ramp_texture()
+    }
+    /// The ramp coloring the terrain, if it isn't textured.
+    pub fn color_ramp(&self) -> Option<&ColorRamp> {
+        self.color_ramp.map(|index| &self.color_ramps[index])
+    }
+
+    /// Switches to the next ramp, going back to textures after the last one.
+    fn next_color_ramp(&mut self) -> error::Result<()> {
+        self.color_ramp = match self.color_ramp {
+            None => Some(0),
+            Some(index) if index + 1 < self.color_ramps.len() => Some(index + 1),
+            Some(_) => None,
+        };
+        self.update_ramp_texture()?;
+
+        match self.color_ramp() {
+            Some(ramp) => println!("Color ramp: {}", ramp.name()),
+            None => println!("Color ramp: none, using textures"),
+        }
+        Ok(())
+    }
+
+    /// The ramp shown by the map and the legend.
+    fn shown_color_ramp(&self) -> ColorRamp {
+        self.color_ramp()
+            .cloned()
+            .unwrap_or_else(|| RampPreset::Spectrum.ramp())
+    }
+
+    fn update_ramp_texture(&mut self) -> error::Result<()> {
+        let ramp = self.shown_color_ramp();
+        self.ramp_texture = build_ramp_texture(self.display(), &ramp)?;
+        Ok(())
+    }
+
+    /// Whether a colorbar of the ramp is drawn over the view, toggled with the L key.
+    ///
+    /// The legend is only drawn while the terrain is colored by a ramp or shown as a
+    /// map.
+    pub fn set_show_legend(&mut self, show_legend: bool) {
+        self.show_legend = show_legend;
+    }
+
+    fn is_legend_visible(&self) -> bool {
+        self.show_legend && (self.color_ramp.is_some() || self.show_map)
+    }
+
+    /// Redraws the legend if the ramp or its labels changed.
+    ///
+    /// The range of an animated terrain changes every frame, but the labels only
+    /// show it to a few decimals, so they rarely need redrawing.
+    fn update_legend(&mut self) -> error::Result<()> {
+        if !self.is_legend_visible() {
+            return Ok(());
+        }
+
+        let raw_range = self.geometry
+            .as_ref()
+            .and_then(|geometry| geometry.borrow().raw_range());
+        let key = (self.color_ramp, legend::labels(raw_range));
+        if self.legend.as_ref().map_or(false, |legend| legend.0 == key) {
+            return Ok(());
+        }
+
+        let img = legend::draw_legend(&self.shown_color_ramp(), &key.1);
+        let dimensions = img.dimensions();
+        let texture = glium::texture::Texture2d::new(
+            self.display(),
+            glium::texture::RawImage2d::from_raw_rgba(img.into_raw(), dimensions),
+        ).map_err(|err| Error::resource("the legend texture", err))?;
+        self.legend = Some((key, texture));
+        Ok(())
+    }
+
+    /// Moves the camera to `position`, looking at `target` with `up` pointing to
+    /// the top of the view.
+    pub fn look_at(&mut self, position: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) {
+        self.camera_controller
+            .borrow_mut()
+            .set_position(position)
+            .set_up(up)
+            .look_at(target);
+    }
+    pub fn set_camera_pose(&mut self, position: Vector3<f32>, theta: f32, phi: f32, fov: f32) {
+        self.camera_controller
+            .borrow_mut()
+            .set_position(position)
+            .set_theta(theta)
+            .set_phi(phi)
+            .set_fov(fov);
+    }
+
+    /// Rebuilds the terrain, materials, light and textures described by `config`, and
+    /// switches to its shading and render settings.
+    ///
+    /// The camera is left alone, so this can be used to switch scenes while running;
+    /// see `set_camera_pose` to apply the camera of the config as well. If `config`
+    /// has no seed, the current one is kept, or a random one is drawn the first time.
+    pub fn apply_config(&mut self, config: &Config) -> error::Result<()> {
+        let mut config = config.clone();
+        let seed = config
+            .noise
+            .seed
+            .or_else(|| self.seed())
+            .unwrap_or_else(builder::random_seed);
+        config.noise.seed = Some(seed);
+
+        let geometry = builder::build_geometry_provider(&config);
+        let materials = build_materials(self.display(), &config.materials)?;
+        self.set_geometry(geometry);
+        self.set_materials(materials);
+        self.set_light(
+            Vector3::from(config.light.direction),
+            Vector4::from(config.light.color),
+        );
+        self.set_asset_dirs(config.asset_dirs.clone())?;
+        self.set_layers(config.layers.clone())?;
+        self.set_color_ramp(config.color_ramp.clone())?;
+        self.set_shading(config.shading);
+        self.set_shader_mode(config.shader_mode);
+        self.set_wireframe(config.wireframe);
+        self.set_triplanar(config.triplanar);
+        self.set_slope_layers(config.slope_layers);
+
+        self.config = Some(config);
+        self.map_view = None;
+        self.show_seed(seed);
+        Ok(())
+    }
+
+    /// The active configuration, with the seed that is actually in use.
+    pub fn config(&self) -> Option<&Config> {
+        self.config.as_ref()
+    }
+
+    /// The seed of the noise shown, which reproduces the same terrain in later runs.
+    pub fn seed(&self) -> Option<u64> {
+        self.config.as_ref().and_then(|config| config.noise.seed)
+    }
+
+    /// Rebuilds the terrain of the active configuration from another seed.
+    pub fn reseed(&mut self, seed: u64) {
+        let geometry = match self.config {
+            Some(ref mut config) => {
+                config.noise.seed = Some(seed);
+                builder::build_geometry_provider(config)
+            }
+            None => return,
+        };
+        self.set_geometry(geometry);
+        self.show_seed(seed);
+    }
+
+    /// The active configuration with the current camera pose and render settings,
+    /// which reproduces what is shown.
+    pub fn view_config(&self) -> Option<Config> {
+        let mut config = self.config.clone()?;
+        let camera = self.camera_controller.borrow();
+        config.camera = CameraConfig {
+            position: cgmath::conv::array3(*camera.position()),
+            theta: camera.theta(),
+            phi: camera.phi(),
+            fov: camera.fov(),
+        };
+        config.shading = self.shading;
+        config.shader_mode = self.shader_mode;
+        config.wireframe = self.is_wireframe;
+        config.triplanar = self.use_triplanar;
+        config.slope_layers = self.use_slope_layers;
+        config.color_ramp = self.color_ramp().cloned();
+        Some(config)
+    }
+
+    /// Encodes the current view into a string that reproduces it, see `share::encode`.
+    pub fn share_string(&self) -> Option<String> {
+        self.view_config().map(|config| share::encode(&config))
+    }
+
+    /// Saves the last frame shown in the window as `screenshot-<time>.png` in the
+    /// working directory, along with a scene file of the same name that recreates it.
+    /// A number is appended to the name rather than overwriting an earlier screenshot.
+    ///
+    /// Returns the path of the image.
+    pub fn save_screenshot(&self) -> error::Result<PathBuf> {
+        let display = self.window_display().ok_or(Error::Window(
+            "a headless visualizer has no window to take screenshots of",
+        ))?;
+        let pixels: glium::texture::RawImage2d<u8> = display.read_front_buffer();
+        let img: image::RgbaImage =
+            image::ImageBuffer::from_raw(pixels.width, pixels.height, pixels.data.into_owned())
+                .ok_or(Error::Window(
+                    "the window returned a frame of the wrong size",
+                ))?;
+
+        let time = timestamp();
+        let stem = (1..)
+            .map(|n| match n {
+                1 => format!("screenshot-{}", time),
+                n => format!("screenshot-{}-{}", time, n),
+            })
+            .find(|stem| {
+                !Path::new(&format!("{}.png", stem)).exists()
+                    && !Path::new(&format!("{}.toml", stem)).exists()
+            })
+            .unwrap();
+        let path = PathBuf::from(format!("{}.png", stem));
+        image::imageops::flip_vertical(&img)
+            .save(&path)
+            .map_err(|err| Error::Io(path.clone(), err))?;
+
+        if let Some(config) = self.view_config() {
+            let scene_path = PathBuf::from(format!("{}.toml", stem));
+            fs::File::create(&scene_path)
+                .and_then(|mut file| {
+                    file.write_all(SceneFile::from_config(&config).to_toml().as_bytes())
+                })
+                .map_err(|err| Error::Io(scene_path, err))?;
+        }
+        Ok(path)
+    }
+
+    fn show_seed(&self, seed: u64) {
+        println!("Seed: {}", seed);
+        if let Some(display) = self.window_display() {
+            display
+                .gl_window()
+                .set_title(&format!("Noise Visualizer - seed {}", seed));
+        }
+    }
+
+    /// Reloads the scene through `watcher` whenever its file changes.
+    pub fn watch_scene(&mut self, watcher: SceneWatcher) {
+        self.scene_watcher = Some(watcher);
+    }
 
-    pub fn run(&mut self) {
+    /// Shows the terrain in the window until it is closed.
+    ///
+    /// Fails if the visualizer is headless.
+    pub fn run(&mut self) -> error::Result<()> {
         let mut running = self.running;
-        self.load_textures();
+        self.load_textures()?;
+        let mut last_frame = Instant::now();
 
         while running {
-            let mut target = self.display().draw();
+            let elapsed = last_frame.elapsed();
+            last_frame = Instant::now();
+            self.time += elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+
+            let mut target = self.window_display()
+                .ok_or(Error::Window(
+                    "a headless visualizer can't be run, use render_image instead",
+                ))?
+                .draw();
             target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
 
-            self.event_loop();
-            self.update();
-            self.draw(&mut target);
+            // The frame has to be finished even if drawing it failed.
+            let drawn = self.step().and_then(|_| self.draw(&mut target));
+            let finished = target.finish().map_err(Error::SwapBuffers);
+            drawn.and(finished)?;
 
-            target.finish().unwrap();
+            // The front buffer only holds the frame once it has been swapped in.
+            if self.screenshot_requested {
+                self.screenshot_requested = false;
+                match self.save_screenshot() {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(err) => eprintln!("could not save screenshot: {}", err),
+                }
+            }
             running = self.running;
         }
+        Ok(())
+    }
+
+    /// Handles input and brings everything drawn up to date for the next frame.
+    fn step(&mut self) -> error::Result<()> {
+        self.event_loop()?;
+        self.update()?;
+        self.update_geometry()?;
+        self.update_legend()
+    }
+
+    /// Draws the terrain as seen from the camera into an image of `size` pixels.
+    ///
+    /// Geometry providers are updated until they are done loading, so the image shows
+    /// the same terrain a window would once it settles.
+    pub fn render_image(&mut self, size: (u32, u32)) -> error::Result<image::RgbaImage> {
+        if self.layer_textures.is_none() {
+            self.load_textures()?;
+        }
+        self.load_geometry()?;
+        self.update_legend()?;
+
+        let facade = self.display();
+        let (width, height) = size;
+        let color = glium::texture::Texture2d::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::U8U8U8U8,
+            glium::texture::MipmapsOption::NoMipmap,
+            width,
+            height,
+        ).map_err(|err| Error::resource("the image to render into", err))?;
+        let depth = glium::framebuffer::DepthRenderBuffer::new(
+            facade,
+            glium::texture::DepthFormat::F32,
+            width,
+            height,
+        ).map_err(|err| Error::resource("the depth buffer to render with", err))?;
+        let mut target =
+            glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, &color, &depth)
+                .map_err(|err| Error::resource("the framebuffer to render into", err))?;
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        self.draw(&mut target)?;
+
+        let pixels: glium::texture::RawImage2d<u8> = color.read();
+        let img: image::RgbaImage =
+            image::ImageBuffer::from_raw(width, height, pixels.data.into_owned()).ok_or_else(
+                || {
+                    Error::Invalid(format!(
+                        "the rendered image isn't {}x{} pixels",
+                        width, height
+                    ))
+                },
+            )?;
+        // OpenGL stores the bottom row first.
+        Ok(image::imageops::flip_vertical(&img))
     }
 
-    fn event_loop(&mut self) {
+    fn event_loop(&mut self) -> error::Result<()> {
         let mut is_closing = false;
         let mut camera_controller = self.camera_controller.borrow_mut();
         let mut is_wireframe = self.is_wireframe;
         let mut is_focused = self.is_focused;
+        let mut shading = self.shading;
+        let mut shader_mode = self.shader_mode;
+        let mut reroll = false;
+        let mut share_requested = self.share_requested;
+        let mut screenshot_requested = self.screenshot_requested;
+        let mut next_ramp_requested = self.next_ramp_requested;
+        let mut show_map = self.show_map;
+        let mut show_legend = self.show_legend;
+        let mut use_triplanar = self.use_triplanar;
+        let mut use_slope_layers = self.use_slope_layers;
+        let mut cursor_position = self.cursor_position;
+        let mut is_panning = self.is_panning;
 
-        let size = self.display.gl_window().get_inner_size().unwrap();
+        let size = match self.window_display() {
+            Some(display) => display
+                .gl_window()
+                .get_inner_size()
+                .ok_or(Error::Window("the window no longer exists"))?,
+            None => return Ok(()),
+        };
+        let mut map_view = match self.map_view {
+            None if show_map => self.bounds().map(|bounds| MapView::fit(&bounds, size)),
+            map_view => map_view,
+        };
+
+        let (events_loop, display) = match self.backend {
+            Backend::Window {
+                ref mut events_loop,
+                ref display,
+            } => (events_loop, display),
+            Backend::Headless(_) => return Ok(()),
+        };
 
-        self.events_loop.poll_events(|ev| match ev {
+        events_loop.poll_events(|ev| match ev {
             glutin::Event::WindowEvent { event, .. } => match event {
                 glutin::WindowEvent::Closed => is_closing = true,
                 glutin::WindowEvent::Focused(val) => {
@@ -149,12 +704,96 @@ impl Visualizer {
                                     is_wireframe = !is_wireframe;
                                 }
                             }
+                            glutin::VirtualKeyCode::F => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    shading = match shading {
+                                        Shading::Smooth => Shading::Flat,
+                                        Shading::Flat => Shading::Smooth,
+                                    };
+                                }
+                            }
+                            glutin::VirtualKeyCode::H => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    shader_mode = shader_mode.next();
+                                    println!("Shader: {}", shader_mode);
+                                }
+                            }
+                            glutin::VirtualKeyCode::R => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    reroll = true;
+                                }
+                            }
+                            glutin::VirtualKeyCode::F12 => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    screenshot_requested = true;
+                                }
+                            }
+                            glutin::VirtualKeyCode::C => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    share_requested = true;
+                                }
+                            }
+                            glutin::VirtualKeyCode::G => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    next_ramp_requested = true;
+                                }
+                            }
+                            glutin::VirtualKeyCode::L => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    show_legend = !show_legend;
+                                }
+                            }
+                            glutin::VirtualKeyCode::Y => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    use_triplanar = !use_triplanar;
+                                    println!(
+                                        "Triplanar mapping: {}",
+                                        if use_triplanar { "on" } else { "off" }
+                                    );
+                                }
+                            }
+                            glutin::VirtualKeyCode::K => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    use_slope_layers = !use_slope_layers;
+                                    println!(
+                                        "Slope layers: {}",
+                                        if use_slope_layers { "on" } else { "off" }
+                                    );
+                                }
+                            }
+                            glutin::VirtualKeyCode::Tab => {
+                                if input.state == glutin::ElementState::Pressed {
+                                    show_map = !show_map;
+                                    is_panning = false;
+                                }
+                            }
                             _ => (),
                         }
                     }
-                    camera_controller.handle_keyboard_input(&input);
+                    if !show_map {
+                        camera_controller.handle_keyboard_input(&input);
+                    }
+                }
+                glutin::WindowEvent::MouseInput {
+                    state,
+                    button: glutin::MouseButton::Left,
+                    ..
+                } => {
+                    is_panning = show_map && state == glutin::ElementState::Pressed;
+                }
+                glutin::WindowEvent::CursorMoved { position, .. } if show_map => {
+                    if let Some(ref mut map_view) = map_view {
+                        if is_panning {
+                            map_view.pan((
+                                position.0 - cursor_position.0,
+                                position.1 - cursor_position.1,
+                            ));
+                        }
+                    }
+                    cursor_position = position;
                 }
                 glutin::WindowEvent::CursorMoved { position, .. } => {
+                    cursor_position = position;
                     let (dx, dy) = (
                         position.0 - (size.0 as f64) / 2.0,
                         position.1 - (size.1 as f64) / 2.0,
@@ -165,6 +804,15 @@ impl Visualizer {
                         camera_controller.handle_mouse_move((dx, dy));
                     }
                 }
+                glutin::WindowEvent::MouseWheel { delta, .. } if show_map => {
+                    if let Some(ref mut map_view) = map_view {
+                        let lines = match delta {
+                            glutin::MouseScrollDelta::LineDelta(_, y) => y,
+                            glutin::MouseScrollDelta::PixelDelta(_, y) => y / 10.0,
+                        };
+                        map_view.zoom(1.1_f32.powf(lines), cursor_position, size);
+                    }
+                }
                 glutin::WindowEvent::MouseWheel { delta, .. } => {
                     camera_controller.handle_mouse_wheel(&delta);
                 }
@@ -176,76 +824,353 @@ impl Visualizer {
         self.running = !is_closing;
         self.is_wireframe = is_wireframe;
         self.is_focused = is_focused;
+        self.shading = shading;
+        self.shader_mode = shader_mode;
+        self.share_requested = share_requested;
+        self.screenshot_requested = screenshot_requested;
+        self.next_ramp_requested = next_ramp_requested;
+        self.show_map = show_map;
+        self.show_legend = show_legend;
+        self.use_triplanar = use_triplanar;
+        self.use_slope_layers = use_slope_layers;
+        self.map_view = map_view;
+        self.cursor_position = cursor_position;
+        self.is_panning = is_panning;
+        if reroll {
+            self.pending_seed = Some(builder::random_seed());
+        }
 
-        if is_focused {
-            self.display
+        // The cursor is only captured for looking around with the camera.
+        if is_focused && !show_map {
+            display
                 .gl_window()
                 .deref()
                 .set_cursor_position(size.0 as i32 / 2, size.1 as i32 / 2)
-                .unwrap();
+                .map_err(|_| {
+                    Error::Window("could not move the cursor to the middle of the window")
+                })?;
         }
+        Ok(())
     }
 
-    fn update(&mut self) {
+    fn update(&mut self) -> error::Result<()> {
+        let reloaded = self.scene_watcher.as_mut().and_then(|w| w.poll());
+        match reloaded {
+            Some(Ok(config)) => {
+                let path = self.scene_watcher.as_ref().unwrap().path().to_path_buf();
+                // Applying can fail halfway, so go back to what was shown before.
+                let previous = self.view_config();
+                match self.apply_config(&config) {
+                    Ok(()) => println!("Reloaded {}", path.display()),
+                    Err(err) => {
+                        eprintln!("{}: {}", path.display(), err);
+                        if let Some(previous) = previous {
+                            self.apply_config(&previous)?;
+                        }
+                    }
+                }
+            }
+            // Keep showing the previous scene until the file is fixed.
+            Some(Err(err)) => eprintln!("{}", err),
+            None => (),
+        }
+
+        if let Some(seed) = self.pending_seed.take() {
+            self.reseed(seed);
+        }
+        if self.next_ramp_requested {
+            self.next_ramp_requested = false;
+            self.next_color_ramp()?;
+        }
+        if self.share_requested {
+            self.share_requested = false;
+            if let Some(s) = self.share_string() {
+                println!("{}", s);
+            }
+        }
+
         if let Some(ref mut f) = self.update_method {
             f();
         }
+        Ok(())
+    }
+
+    /// Whether meshes should be built with smooth or faceted normals.
+    ///
+    /// Toggled with the F key; geometry providers are expected to check it in
+    /// `GeometryProvider::update` and rebuild their meshes when it changes.
+    pub fn shading(&self) -> Shading {
+        self.shading
+    }
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.shading = shading;
+    }
+    pub fn shader_mode(&self) -> ShaderMode {
+        self.shader_mode
+    }
+    pub fn set_shader_mode(&mut self, shader_mode: ShaderMode) {
+        self.shader_mode = shader_mode;
+    }
+    pub fn set_wireframe(&mut self, is_wireframe: bool) {
+        self.is_wireframe = is_wireframe;
+    }
+    pub fn set_triplanar(&mut self, use_triplanar: bool) {
+        self.use_triplanar = use_triplanar;
+    }
+    /// Without slope layers, the layers limited to a range of slopes are left out and
+    /// the others are picked by height only.
+    pub fn set_slope_layers(&mut self, use_slope_layers: bool) {
+        self.use_slope_layers = use_slope_layers;
+    }
+
+    /// Seconds of animation time, which follows the wall clock while running and is
+    /// set explicitly when recording.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    pub fn camera_position(&self) -> Vector3<f32> {
+        *self.camera_controller.borrow().position()
     }
 
     pub fn set_update_fn(&mut self, f: Box<FnMut()>) {
         self.update_method = Some(f);
     }
 
-    fn draw(&self, target: &mut glium::Frame) {
+    /// Updates the geometry provider until it has loaded everything around the camera.
+    pub fn load_geometry(&self) -> error::Result<()> {
+        self.update_geometry()?;
+        while self.geometry
+            .as_ref()
+            .map_or(false, |geometry| geometry.borrow().is_loading())
+        {
+            self.update_geometry()?;
+        }
+        Ok(())
+    }
+
+    /// The world space bounds of the geometry drawn in the last frame.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let geometry = self.geometry.as_ref()?.borrow();
+        let bounds = geometry
+            .geometries()
+            .iter()
+            .filter_map(|g| g.bounds())
+            .fold(None, |acc: Option<Bounds>, b| {
+                Some(acc.map_or(b, |acc| acc.union(&b)))
+            });
+        bounds
+    }
+
+    fn update_geometry(&self) -> error::Result<()> {
+        match self.geometry {
+            Some(ref geometry) => geometry.borrow_mut().update(self),
+            None => Ok(()),
+        }
+    }
+
+    fn draw<S: Surface>(&self, target: &mut S) -> error::Result<()> {
+        if self.show_map {
+            self.draw_map(target)?;
+        } else {
+            self.draw_terrain(target)?;
+        }
+        if self.is_legend_visible() {
+            self.draw_legend(target)?;
+        }
+        Ok(())
+    }
+
+    fn draw_terrain<S: Surface>(&self, target: &mut S) -> error::Result<()> {
+        let (width, height) = target.get_dimensions();
         let (mut view, perspective) = self.camera_controller
             .borrow()
-            .make_view_perspective_matrix(4.0 / 3.0, 5.0, 1000.0);
+            .make_view_perspective_matrix(width as f32 / height as f32, 5.0, 1000.0);
         let reflect = build_x_reflection_matrix();
         view = reflect * view;
 
-        let mut geom_provider = self.geometry.as_ref().unwrap().borrow_mut();
-        geom_provider.update(self);
-        let geom = geom_provider.get_geometry();
-
-        let vertex_buffer = geom.vertex_buffer();
-        let index_buffer = geom.index_buffer();
-        let model = *geom.model();
+        let geom_provider = match self.geometry {
+            Some(ref geometry) => geometry.borrow(),
+            None => return Ok(()),
+        };
 
-        let light_uniforms = glium::uniforms::UniformBuffer::new(
-            self.display(),
-            Lights {
-                light_pos: cgmath::conv::array3(Vector3::new(100.0, -0.0, -500.0_f32).normalize()),
-                light_color: cgmath::conv::array4(Vector4::new(1.0, 1.0, 1.0, 1.0)),
-            },
-        ).unwrap();
+        let light_uniforms = glium::uniforms::UniformBuffer::new(self.display(), self.lights)
+            .map_err(|err| Error::resource("the light uniforms", err))?;
 
         let material_uniforms = self.materials
             .as_ref()
-            .expect("Visualizer requires materials to be set");
+            .ok_or_else(|| Error::Invalid("the terrain has no materials".to_string()))?;
+        let layer_textures = self.layer_textures
+            .as_ref()
+            .ok_or_else(|| Error::Invalid("the layer textures aren't loaded".to_string()))?;
 
-        let normal_mat = mat4_to_mat3(model).invert().unwrap().transpose();
         let draw_params = self.get_draw_params();
+        let (program, debug_view) = self.terrain_programs.get(self.shader_mode);
+        let depth_range = self.bounds()
+            .map_or((0.0, 1.0), |bounds| bounds.distance_range(self.camera_position()));
+
+        for geom in geom_provider.geometries() {
+            let vertex_buffer = geom.vertex_buffer();
+            let index_buffer = geom.index_buffer();
+            let model = *geom.model();
+            let normal_mat = mat4_to_mat3(model)
+                .invert()
+                .ok_or_else(|| {
+                    Error::Invalid(
+                        "the model matrix has a zero scale, so normals can't be transformed"
+                            .to_string(),
+                    )
+                })?
+                .transpose();
+
+            target
+                .draw(
+                    vertex_buffer,
+                    index_buffer,
+                    program,
+                    &uniform! {
+                        perspective: cgmath::conv::array4x4(perspective),
+                        view: cgmath::conv::array4x4(view),
+                        model: cgmath::conv::array4x4(model),
+                        normal_model: cgmath::conv::array3x3(normal_mat),
+                        Lights: &light_uniforms,
+                        Materials: material_uniforms,
+                        Layers: &self.layer_uniforms,
+                        num_layers: self.layers.len() as i32,
+                        layer_textures: layer_textures.sampled(),
+                        texture_density: geom.texture_density().unwrap_or(1.0),
+                        use_triplanar: self.use_triplanar,
+                        use_slope_layers: self.use_slope_layers,
+                        color_ramp: self.ramp_sampler(),
+                        use_color_ramp: self.color_ramp.is_some(),
+                        debug_view: debug_view,
+                        depth_range: [depth_range.0, depth_range.1],
+                    },
+                    &draw_params,
+                )
+                .map_err(|err| Error::Draw("the terrain", err))?;
+        }
+        Ok(())
+    }
+
+    /// Draws the terrain from above, colored by height with the active color ramp.
+    fn draw_map<S: Surface>(&self, target: &mut S) -> error::Result<()> {
+        let size = target.get_dimensions();
+        let map_view = match self.map_view
+            .or_else(|| self.bounds().map(|bounds| MapView::fit(&bounds, size)))
+        {
+            Some(map_view) => map_view,
+            None => return Ok(()),
+        };
+        let view_projection = map_view.matrix(size);
 
+        let geom_provider = match self.geometry {
+            Some(ref geometry) => geometry.borrow(),
+            None => return Ok(()),
+        };
+
+        // Meshes are drawn flat, so depth testing would only hide some of them.
+        let draw_params = glium::DrawParameters {
+            polygon_mode: self.get_draw_params().polygon_mode,
+            ..Default::default()
+        };
+
+        for geom in geom_provider.geometries() {
+            target
+                .draw(
+                    geom.vertex_buffer(),
+                    geom.index_buffer(),
+                    &self.map_program,
+                    &uniform! {
+                        view_projection: cgmath::conv::array4x4(view_projection),
+                        model: cgmath::conv::array4x4(*geom.model()),
+                        color_ramp: self.ramp_sampler(),
+                    },
+                    &draw_params,
+                )
+                .map_err(|err| Error::Draw("the map", err))?;
+        }
+        Ok(())
+    }
+
+    /// Draws the legend in the top right corner, one texel per pixel.
+    fn draw_legend<S: Surface>(&self, target: &mut S) -> error::Result<()> {
+        let texture = match self.legend {
+            Some((_, ref texture)) => texture,
+            None => return Ok(()),
+        };
+
+        let (width, height) = target.get_dimensions();
+        let to_ndc = |x: u32, y: u32| {
+            [
+                2.0 * x as f32 / width as f32 - 1.0,
+                1.0 - 2.0 * y as f32 / height as f32,
+            ]
+        };
+        let right = width.saturating_sub(LEGEND_MARGIN);
+        let left = right.saturating_sub(texture.width());
+        let top = LEGEND_MARGIN;
+        let bottom = top + texture.height();
+
+        // The first row of the image is at the bottom of the texture.
+        let vertices = [
+            OverlayVertex {
+                position: to_ndc(left, top),
+                tex_coord: [0.0, 0.0],
+            },
+            OverlayVertex {
+                position: to_ndc(right, top),
+                tex_coord: [1.0, 0.0],
+            },
+            OverlayVertex {
+                position: to_ndc(left, bottom),
+                tex_coord: [0.0, 1.0],
+            },
+            OverlayVertex {
+                position: to_ndc(right, bottom),
+                tex_coord: [1.0, 1.0],
+            },
+        ];
+        let vertex_buffer = glium::VertexBuffer::new(self.display(), &vertices)
+            .map_err(|err| Error::resource("the legend vertex buffer", err))?;
+
+        let draw_params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
         target
             .draw(
-                vertex_buffer,
-                index_buffer,
-                &self.shader_program,
+                &vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+                &self.overlay_program,
                 &uniform! {
-                    perspective: cgmath::conv::array4x4(perspective),
-                    view: cgmath::conv::array4x4(view),
-                    model: cgmath::conv::array4x4(model),
-                    normal_model: cgmath::conv::array3x3(normal_mat),
-                    Lights: &light_uniforms,
-                    Materials: material_uniforms,
-                    grass_texture: self.textures[0].sampled(),
-                    dirt_texture: self.textures[1].sampled(),
-                    snow_texture: self.textures[2].sampled(),
-                    water_texture: self.textures[3].sampled(),
+                    overlay: texture
+                        .sampled()
+                        .minify_filter(uniforms::MinifySamplerFilter::Nearest)
+                        .magnify_filter(uniforms::MagnifySamplerFilter::Nearest),
                 },
                 &draw_params,
             )
-            .unwrap();
+            .map_err(|err| Error::Draw("the legend", err))
+    }
+
+    fn ramp_sampler(&self) -> uniforms::Sampler<glium::texture::Texture1d> {
+        self.ramp_texture
+            .sampled()
+            .wrap_function(uniforms::SamplerWrapFunction::Clamp)
+            .minify_filter(uniforms::MinifySamplerFilter::Linear)
+            .magnify_filter(uniforms::MagnifySamplerFilter::Linear)
+    }
+
+    /// Whether the terrain is shown as a flat map, toggled with the Tab key.
+    pub fn is_showing_map(&self) -> bool {
+        self.show_map
+    }
+    pub fn set_show_map(&mut self, show_map: bool) {
+        self.show_map = show_map;
     }
 
     pub fn get_draw_params(&self) -> glium::DrawParameters {
@@ -265,37 +1190,158 @@ impl Visualizer {
         }
     }
 
-    pub fn load_textures(&mut self) {
-        let grass_texture =
-            load_texture(self.display(), Path::new("./Assets/grass_texture_1.jpg")).unwrap();
-        let dirt_texture = load_texture(
-            self.display(),
-            Path::new("./Assets/Orange dirtl texture-1.jpg"),
-        ).unwrap();
-        //let snow_texture = load_texture(self.display(), Path::new("./Assets/snow_texture_1.jpg")).unwrap();
-        let stone_texture =
-            load_texture(self.display(), Path::new("./Assets/stone_texture_1.jpg")).unwrap();
-        let water_texture =
-            load_texture(self.display(), Path::new("./Assets/water_texture_1.jpg")).unwrap();
-
-        self.textures.push(grass_texture);
-        self.textures.push(dirt_texture);
-        self.textures.push(stone_texture);
-        self.textures.push(water_texture);
+    /// Loads or generates the texture of every layer, with a placeholder in the color
+    /// the terrain ramp has over the layer's heights for the ones that can't be loaded.
+    pub fn load_textures(&mut self) -> error::Result<()> {
+        let terrain = RampPreset::Terrain.ramp();
+        // The textures that loaded, or the colors of the placeholders to replace the
+        // others with.
+        let loaded: Vec<Result<image::RgbaImage, [f32; 4]>> = self
+            .layers
+            .iter()
+            .map(|layer| match layer.texture {
+                TextureSource::File(ref path) => self.assets.load_image(path).map_err(|err| {
+                    eprintln!("warning: {}; using a placeholder texture", err);
+                    terrain.color_at((layer.height[0] + layer.height[1]) / 2.0)
+                }),
+                TextureSource::Procedural(ref texture) => Ok(texture.generate()),
+            })
+            .collect();
+
+        // Placeholders take the size of the largest texture, so that the textures
+        // that did load aren't shrunk to theirs.
+        let size = loaded
+            .iter()
+            .filter_map(|img| img.as_ref().ok())
+            .map(|img| img.dimensions())
+            .max_by_key(|&(width, height)| u64::from(width) * u64::from(height))
+            .unwrap_or((assets::PLACEHOLDER_SIZE, assets::PLACEHOLDER_SIZE));
+        let images = loaded
+            .into_iter()
+            .map(|img| img.unwrap_or_else(|color| assets::placeholder_texture(color, size)))
+            .collect();
+
+        let textures = build_layer_textures(self.display(), images)?;
+        self.layer_textures = Some(textures);
+        Ok(())
+    }
+}
+
+fn build_materials(
+    display: &Facade,
+    materials: &[MaterialConfig],
+) -> error::Result<uniforms::UniformBuffer<Materials>> {
+    let materials: Vec<_> = materials
+        .iter()
+        .map(|m| Material {
+            ambient: m.ambient,
+            diffuse: m.diffuse,
+            specular: m.specular,
+            shine: m.shine,
+            _padding: Default::default(),
+        })
+        .collect();
+
+    uniforms::UniformBuffer::new(display, Materials::new(&materials))
+        .map_err(|err| Error::resource("the material uniforms", err))
+}
+
+fn build_ramp_texture(
+    display: &Facade,
+    ramp: &ColorRamp,
+) -> error::Result<glium::texture::Texture1d> {
+    glium::texture::Texture1d::new(display, ramp.bake(RAMP_RESOLUTION))
+        .map_err(|err| Error::resource("the color ramp texture", err))
+}
+
+fn build_layer_uniforms(
+    display: &Facade,
+    layers: &[LayerConfig],
+) -> error::Result<uniforms::UniformBuffer<Layers>> {
+    let mut uniforms = Layers::default();
+    for (uniform, layer) in uniforms.layer.iter_mut().zip(layers) {
+        *uniform = Layer {
+            height: layer.height,
+            slope: layer.slope,
+            scale: layer.scale,
+            blend: layer.blend,
+            _padding: Default::default(),
+        };
     }
+
+    uniforms::UniformBuffer::new(display, uniforms)
+        .map_err(|err| Error::resource("the layer uniforms", err))
 }
 
-fn load_texture(display: &glium::Display, path: &Path) -> io::Result<glium::texture::Texture2d> {
-    let file = fs::File::open(path)?;
-    let reader = io::BufReader::new(file);
+/// Uploads the textures of the layers into an array, in the same order.
+///
+/// All the images of an array have the same size, so the ones that differ from the
+/// largest are resized to it.
+fn build_layer_textures(
+    display: &Facade,
+    images: Vec<image::RgbaImage>,
+) -> error::Result<glium::texture::Texture2dArray> {
+    let dimensions = images
+        .iter()
+        .map(|img| img.dimensions())
+        .max_by_key(|&(width, height)| u64::from(width) * u64::from(height))
+        .ok_or_else(|| Error::Invalid("there are no texture layers".to_string()))?;
+    let data = images
+        .into_iter()
+        .map(|img| {
+            let (width, height) = dimensions;
+            let img = if img.dimensions() == dimensions {
+                img
+            } else {
+                image::imageops::resize(&img, width, height, image::FilterType::Triangle)
+            };
+            glium::texture::RawImage2d::from_raw_rgba(img.into_raw(), dimensions)
+        })
+        .collect();
 
-    let img = image::load(reader, image::ImageFormat::JPEG)
-        .unwrap()
-        .to_rgba();
-    let img_dimensions = img.dimensions();
+    glium::texture::Texture2dArray::new(display, data)
+        .map_err(|err| Error::resource("the layer texture array", err))
+}
 
-    let texture_data = glium::texture::RawImage2d::from_raw_rgba(img.into_raw(), img_dimensions);
-    Ok(glium::texture::Texture2d::new(display, texture_data).unwrap())
+impl Backend {
+    fn facade(&self) -> &Facade {
+        match *self {
+            Backend::Window { ref display, .. } => display,
+            Backend::Headless(ref renderer) => renderer,
+        }
+    }
+}
+
+/// The current UTC time as `YYYYMMDD-HHMMSS-mmm`, down to the millisecond.
+fn timestamp() -> String {
+    let (secs, millis) = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| (t.as_secs(), t.subsec_nanos() / 1_000_000))
+        .unwrap_or((0, 0));
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        millis
+    )
 }
 
 fn build_x_reflection_matrix() -> Matrix4<f32> {